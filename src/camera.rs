@@ -0,0 +1,85 @@
+use super::cpu::camera::{ImageSource, SENSOR_HEIGHT, SENSOR_WIDTH};
+use image::{imageops::FilterType, DynamicImage};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Crops and scales an image to the size of the sensor, and converts it to grayscale
+fn to_sensor_pixels(image: DynamicImage) -> Vec<u8> {
+    image
+        .resize_to_fill(
+            SENSOR_WIDTH as u32,
+            SENSOR_HEIGHT as u32,
+            FilterType::Triangle,
+        )
+        .to_luma8()
+        .into_raw()
+}
+
+/// Image source that always shows the same image
+pub struct ImageFile {
+    pixels: Vec<u8>,
+}
+
+impl ImageFile {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let image = image::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(Self {
+            pixels: to_sensor_pixels(image),
+        })
+    }
+}
+
+impl ImageSource for ImageFile {
+    fn capture(&mut self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+}
+
+/// Image source that shows the PNG files of a folder in alphabetical order,
+/// moving to the next one on every capture
+pub struct ImageFolder {
+    frames: Vec<PathBuf>,
+    index: usize,
+}
+
+impl ImageFolder {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut frames: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+            })
+            .collect();
+        if frames.is_empty() {
+            return Err(format!("{} doesn't contain any PNG files", path.display()));
+        }
+        frames.sort();
+        Ok(Self { frames, index: 0 })
+    }
+}
+
+impl ImageSource for ImageFolder {
+    fn capture(&mut self) -> Vec<u8> {
+        let path = &self.frames[self.index];
+        self.index = (self.index + 1) % self.frames.len();
+        // Frames are read when they're needed, so the folder can be large
+        match image::open(path) {
+            Ok(image) => to_sensor_pixels(image),
+            Err(e) => {
+                eprintln!("Failed to read camera frame {}: {e}", path.display());
+                vec![0; SENSOR_WIDTH * SENSOR_HEIGHT]
+            }
+        }
+    }
+}
+
+/// Opens a folder of frames or a single image file as an image source
+pub fn open_image_source(path: &Path) -> Result<Box<dyn ImageSource>, String> {
+    if path.is_dir() {
+        Ok(Box::new(ImageFolder::open(path)?))
+    } else {
+        Ok(Box::new(ImageFile::open(path)?))
+    }
+}
@@ -4,21 +4,43 @@ use serde_big_array::BigArray;
 use std::ops::RangeInclusive;
 
 pub mod apu;
+pub mod boot;
+pub mod camera;
+pub mod cgb;
+pub mod colorization;
+pub mod huc3;
 pub mod input;
 pub mod interrupts;
+pub mod mbc7;
 pub mod memory;
+pub mod mmm01;
 pub mod ppu;
 pub mod readwrite;
 pub mod registers;
+pub mod rtc;
+pub mod scheduler;
+pub mod serial;
+pub mod sgb;
 pub mod timer;
-use super::Options;
+use super::emulator::EmulatorOptions;
 use apu::*;
+use boot::*;
+use camera::*;
+use cgb::*;
+use colorization::*;
+use huc3::*;
 use input::*;
 use interrupts::*;
+use mbc7::*;
 use memory::*;
+use mmm01::*;
 use ppu::*;
 use readwrite::*;
 use registers::*;
+use rtc::*;
+use scheduler::*;
+use serial::*;
+use sgb::*;
 use timer::*;
 
 /// The main processing unit
@@ -30,81 +52,109 @@ pub struct CPU {
     pub ppu: PPU,
     pub apu: APU,
     pub timer: Timer,
+    pub serial: Serial,
     pub input: InputReg,
     pub istate: InterruptState,
+    pub speed: SpeedSwitch,
+    pub hdma: HDMA,
+    pub sgb: SGB,
+    pub scheduler: Scheduler,
     pub halt: bool,
     pub profiling: bool,
+    /// If VRAM and OAM are cut off from the CPU like on hardware.
+    /// Can be turned off from the debugger
+    pub access_restrictions: bool,
 }
 
 impl CPU {
-    pub fn new(rom_file: Vec<u8>, options: &Options) -> Self {
-        Self {
+    pub fn new(rom_file: Vec<u8>, options: &EmulatorOptions) -> Self {
+        let compat_palettes = options.colorization.palettes(&rom_file);
+        let mut cpu = Self {
             reg: Registers::new(),
             ppu: PPU::new(),
             apu: APU::new(options.audio_sample_rate),
-            mem: Memory::new(rom_file),
+            mem: Memory::new(
+                rom_file,
+                options
+                    .boot_rom
+                    .clone()
+                    .unwrap_or(FALLBACK_BOOT_ROM.to_vec()),
+            ),
             timer: Timer::new(),
+            serial: Serial::new(),
             input: InputReg::new(),
             istate: InterruptState::new(),
+            speed: SpeedSwitch::new(),
+            hdma: HDMA::new(),
+            sgb: SGB::new(),
+            scheduler: Scheduler::new(),
             halt: false,
             profiling: false,
+            access_restrictions: true,
+        };
+        // CGB games are always run on a CGB
+        if options.sgb && !cpu.mem.info.cgb {
+            cpu.sgb.enabled = true;
+            cpu.sgb.accepts_commands = cpu.mem.info.sgb;
+            cpu.input.sgb = cpu.mem.info.sgb;
+        }
+        // CGB games pick their own colors, and the Super Game Boy colors the screen itself
+        if let Some(palettes) = compat_palettes.filter(|_| !cpu.mem.info.cgb && !cpu.sgb.enabled) {
+            cpu.ppu.load_compat_palettes(&palettes);
         }
+        cpu
     }
 
-    /// Emulates the rest of the Game Boy (apart from instructions) for given amount of M-cycles
+    /// Emulates the rest of the Game Boy (apart from instructions) for given amount of M-cycles.
+    /// Components are only caught up once they have something to do
     pub fn cycle(&mut self, cycles: u8) {
+        #[cfg(feature = "puffin")]
         puffin::profile_function_if!(self.profiling);
-        // Rest of the system runs on T-cycles, which is 1/4 of an M-cycle
-        for _ in 0..(4 * cycles) {
-            // Check if OAM DMA should be started
-            if self.ppu.oam_dma_timer == 640 {
-                self.oam_dma(self.ppu.oam_dma_source);
-            }
-            // Cycle PPU
-            {
-                puffin::profile_scope_if!(self.profiling, "PPU");
-                self.ppu.cycle();
-                self.request_interrupt(self.ppu.interrupt_request);
-            }
-            // Cycle timer
-            {
-                puffin::profile_scope_if!(self.profiling, "Timer");
-                self.timer.cycle();
-                if self.timer.request_interrupt {
-                    self.request_interrupt(InterruptFlag::TIMER);
-                }
-            }
-
-            // Cycle APU based on timer state
-            {
-                puffin::profile_scope_if!(self.profiling, "APU");
-                self.apu.cycle(self.timer.div);
+        // Handle a packet sent to the Super Game Boy by the previous instruction
+        if let Some(packet) = self.input.sgb_packet.take() {
+            self.sgb_packet(packet);
+        }
+        for _ in 0..cycles {
+            // OAM DMA runs on M-cycles, even in double speed mode
+            if self.ppu.oam_dma.active || self.ppu.oam_dma.delay > 0 {
+                self.oam_dma_cycle();
             }
+            self.scheduler.now += 4;
         }
+        self.run_events();
     }
 
     /// Executes the next instruction at program counter.
     /// Ticks the rest of the system at correct points between the execution of instructions.
-    /// Returns whether or not the system hit VBlank during execution
+    /// Returns whether or not a frame ended during execution,
+    /// which happens at VBlank or every frame's worth of time while the LCD is off
     pub fn execute(&mut self) -> bool {
-        let start_vblank = self.ppu.mode == 1;
+        self.ppu.frame_ended = false;
         // Check for possible interrupt requests
         self.check_for_interrupt();
+        // Finish a general purpose HDMA transfer started by the previous instruction
+        if self.hdma.general_pending {
+            self.hdma_general();
+        }
 
         if self.halt {
             // CPU doesn't execute anything when HALTed,
-            // so just cycle the system forward until HALT is lifted
-            self.cycle(1);
-
-            // Check current PPU mode and return true
-            // if it was changed to VBlank during execution
-            let end_vblank = self.ppu.mode == 1;
-            return !start_vblank && end_vblank;
+            // so just cycle the system forward to the next event, which might lift HALT
+            let cycles = (self
+                .scheduler
+                .next_event()
+                .saturating_sub(self.scheduler.now))
+            .div_ceil(4)
+            .clamp(1, u8::MAX as u64);
+            self.cycle(cycles as u8);
+
+            return self.ppu.frame_ended;
         }
 
         let opcode = self.read(self.reg.pc);
         let mut increment_pc = true;
         {
+            #[cfg(feature = "puffin")]
             puffin::profile_scope_if!(self.profiling, "Instruction", format!("{:#06x}", opcode));
             match opcode {
                 0x00..=0x3F => {
@@ -115,9 +165,7 @@ impl CPU {
                             // NOP
                             0x00 => {}
                             // STOP
-                            0x10 => {
-                                eprintln!("Tried to STOP");
-                            }
+                            0x10 => self.stop(),
                             // JR
                             _ => {
                                 let step = self.read_operand() as i8;
@@ -586,10 +634,7 @@ impl CPU {
         // Every instruction takes at least one M-cycle to execute
         self.cycle(1);
 
-        // Check current PPU mode and return true
-        // if it was changed to VBlank during execution
-        let end_vblank = self.ppu.mode == 1;
-        !start_vblank && end_vblank
+        self.ppu.frame_ended
     }
 
     /// Executes the 16-bit long arithmetic opcodes that start with 0xCB
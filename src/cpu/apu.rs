@@ -158,6 +158,13 @@ impl SquareChannel {
         }
     }
 
+    /// Same as calling `update_period` given amount of times
+    pub fn advance_period(&mut self, updates: u64) {
+        let (period_div, steps) = advance_period_divider(self.period_div, self.period, updates);
+        self.period_div = period_div;
+        self.duty_cycle_pointer = ((self.duty_cycle_pointer as u64 + steps) % 8) as u8;
+    }
+
     pub fn trigger(&mut self) {
         self.on = true;
         self.period = self.initial_period;
@@ -282,6 +289,13 @@ impl WaveChannel {
         }
     }
 
+    /// Same as calling `update_period` given amount of times
+    pub fn advance_period(&mut self, updates: u64) {
+        let (period_div, steps) = advance_period_divider(self.period_div, self.period, updates);
+        self.period_div = period_div;
+        self.wave_pointer = ((self.wave_pointer as u64 + steps) % 32) as u8;
+    }
+
     pub fn trigger(&mut self) {
         self.on = true;
         self.period = self.initial_period;
@@ -449,6 +463,22 @@ impl NoiseChannel {
         }
     }
 
+    /// Same as calling `update_lfsr` given amount of times
+    pub fn advance_lfsr(&mut self, updates: u64) {
+        let mut updates = updates;
+        while updates > 0 {
+            // Timer is only incremented until the LFSR is shifted
+            let idle = self.lfsr_pace.saturating_sub(self.lfsr_timer) as u64;
+            if updates <= idle {
+                self.lfsr_timer += updates as u16;
+                return;
+            }
+            self.lfsr_timer += idle as u16;
+            self.update_lfsr();
+            updates -= idle + 1;
+        }
+    }
+
     pub fn trigger(&mut self) {
         self.on = true;
         self.lfsr = 0;
@@ -484,6 +514,19 @@ bitflags! {
     }
 }
 
+/// Runs a period divider for given amount of updates. The divider counts up from the period
+/// and steps the channel forward when it overflows past $7FF.
+/// Returns the new divider value and the amount of steps
+fn advance_period_divider(period_div: u16, period: u16, updates: u64) -> (u16, u64) {
+    let until_step = 0x800 - period_div as u64;
+    if updates < until_step {
+        return (period_div + updates as u16, 0);
+    }
+    let updates = updates - until_step;
+    let interval = 0x800 - period as u64;
+    (period + (updates % interval) as u16, 1 + updates / interval)
+}
+
 /// Audio processing unit
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Deserialize, Serialize)]
@@ -527,10 +570,68 @@ impl APU {
         }
     }
 
-    pub fn cycle(&mut self, timer_div: u16) {
+    /// Cycles the APU forward by given amount of T-cycles, skipping ahead to the cycles
+    /// where DIV-APU is incremented or a sample is output. The divider starts from given value
+    /// and is incremented by given step every cycle, and DIV-APU follows the given bit of it
+    pub fn run(&mut self, cycles: u64, div: u16, step: u16, bit: u8) {
+        let mut div = div;
+        let mut left = cycles;
+        while left > 0 {
+            // First cycle is always run normally, in case the divider was just reset
+            if left < cycles {
+                let idle = self.idle_cycles(div, step, bit).min(left);
+                self.skip(idle);
+                div = div.wrapping_add((idle as u16).wrapping_mul(step));
+                self.last_div_bit = (div >> bit) & 0b1 > 0;
+                left -= idle;
+                if left == 0 {
+                    break;
+                }
+            }
+            div = div.wrapping_add(step);
+            self.cycle((div >> bit) & 0b1 > 0);
+            left -= 1;
+        }
+    }
+
+    /// Returns the amount of T-cycles until the divider bit that DIV-APU follows falls
+    fn cycles_until_div_apu(div: u16, step: u16, bit: u8) -> u64 {
+        let period = 1u64 << (bit + 1);
+        let next_fall = (div as u64 / period + 1) * period;
+        (next_fall - div as u64).div_ceil(step as u64)
+    }
+
+    /// Returns the amount of upcoming T-cycles where only the channel periods are updated
+    fn idle_cycles(&self, div: u16, step: u16, bit: u8) -> u64 {
+        let until_sample = (self.sample_delay - self.sample_delay_counter) as u64;
+        until_sample.min(Self::cycles_until_div_apu(div, step, bit) - 1)
+    }
+
+    /// Returns the amount of T-cycles until DIV-APU is incremented
+    pub fn next_event(&self, div: u16, step: u16, bit: u8) -> u64 {
+        // Divider was just reset, so DIV-APU might be incremented right away
+        if self.last_div_bit != ((div >> bit) & 0b1 > 0) {
+            return 1;
+        }
+        Self::cycles_until_div_apu(div, step, bit)
+    }
+
+    /// Updates the channel periods for given amount of T-cycles, without outputting samples
+    fn skip(&mut self, cycles: u64) {
+        let counter = self.period_delay_counter as u64;
+        let updates = |interval: u64| (counter + cycles) / interval - counter / interval;
+        self.wave_channel.advance_period(updates(2));
+        self.square_channel_1.advance_period(updates(4));
+        self.square_channel_2.advance_period(updates(4));
+        self.noise_channel.advance_lfsr(updates(16));
+        self.period_delay_counter = ((counter + cycles) % 0x100) as u8;
+        self.sample_delay_counter += cycles as u32;
+    }
+
+    /// Cycles the APU forward by one T-cycle, given the value of the divider bit DIV-APU follows
+    fn cycle(&mut self, div_bit: bool) {
         // Increment DIV-APU when DIV register bit 4 (actual divider bit 12)
         // goes from 1 to 0
-        let div_bit = timer_div & 0b1_0000_0000_0000 > 0;
         if self.last_div_bit && !div_bit {
             self.div_apu = self.div_apu.wrapping_add(1);
             // Update length timers at 256hz (every 2 ticks)
@@ -645,7 +746,9 @@ impl MemoryAccess for APU {
             0xFF24 => ((self.left_volume - 1) << 4) | (self.right_volume - 1),
             0xFF25 => self.pan_options.bits(),
             0xFF26 => {
+                // Unused bits read as 1
                 ((self.on as u8) << 7)
+                    | 0b0111_0000
                     | ((self.square_channel_2.on as u8) << 1)
                     | (self.square_channel_1.on as u8)
             }
@@ -0,0 +1,146 @@
+//! Free replacement for the DMG boot ROM, used when no boot ROM file is given.
+//! It draws the logo from the cartridge header, scrolls it down and plays the chime,
+//! leaving the hardware in the same state as the original boot ROM.
+//! Doesn't verify the logo or the header checksum, so every cartridge is started.
+
+/// Size of the DMG boot ROM, which is mapped over $0000-$00FF
+pub const BOOT_ROM_SIZE: usize = 0x100;
+
+#[rustfmt::skip]
+pub const FALLBACK_BOOT_ROM: [u8; BOOT_ROM_SIZE] = [
+    // Set up stack and clear VRAM
+    0x31, 0xFE, 0xFF,                // $0000: ld sp, $FFFE
+    0xAF,                            // $0003: xor a
+    0x21, 0x00, 0x80,                // $0004: ld hl, $8000
+    0x22,                            // $0007: clear_vram: ld [hl+], a
+    0xCB, 0x6C,                      // $0008: bit 5, h
+    0x28, 0xFB,                      // $000A: jr z, clear_vram
+    // Turn on audio and set up channel 1 for the chime
+    0x3E, 0x80,                      // $000C: ld a, $80
+    0xE0, 0x26,                      // $000E: ldh [NR52], a
+    0xE0, 0x11,                      // $0010: ldh [NR11], a
+    0x3E, 0xF3,                      // $0012: ld a, $F3
+    0xE0, 0x12,                      // $0014: ldh [NR12], a
+    0xE0, 0x25,                      // $0016: ldh [NR51], a
+    0x3E, 0x77,                      // $0018: ld a, $77
+    0xE0, 0x24,                      // $001A: ldh [NR50], a
+    0x3E, 0xFC,                      // $001C: ld a, $FC
+    0xE0, 0x47,                      // $001E: ldh [BGP], a
+    // Scale logo from the cartridge header into tiles 1-24
+    0x11, 0x04, 0x01,                // $0020: ld de, $0104
+    0x21, 0x10, 0x80,                // $0023: ld hl, $8010
+    0x1A,                            // $0026: logo: ld a, [de]
+    0xCD, 0x87, 0x00,                // $0027: call draw_logo_byte
+    0x13,                            // $002A: inc de
+    0x7B,                            // $002B: ld a, e
+    0xFE, 0x34,                      // $002C: cp $34
+    0x20, 0xF6,                      // $002E: jr nz, logo
+    // Copy trademark symbol into tile 25
+    0x11, 0xB9, 0x00,                // $0030: ld de, trademark
+    0x06, 0x08,                      // $0033: ld b, 8
+    0x1A,                            // $0035: copy_trademark: ld a, [de]
+    0x13,                            // $0036: inc de
+    0x22,                            // $0037: ld [hl+], a
+    0x23,                            // $0038: inc hl
+    0x05,                            // $0039: dec b
+    0x20, 0xF9,                      // $003A: jr nz, copy_trademark
+    // Place logo tiles in the middle of the tile map
+    0x21, 0x04, 0x99,                // $003C: ld hl, $9904
+    0x3E, 0x01,                      // $003F: ld a, 1
+    0x22,                            // $0041: top_row: ld [hl+], a
+    0x3C,                            // $0042: inc a
+    0xFE, 0x0D,                      // $0043: cp 13
+    0x20, 0xFA,                      // $0045: jr nz, top_row
+    0x36, 0x19,                      // $0047: ld [hl], 25
+    0x2E, 0x24,                      // $0049: ld l, $24
+    0x22,                            // $004B: bottom_row: ld [hl+], a
+    0x3C,                            // $004C: inc a
+    0xFE, 0x19,                      // $004D: cp 25
+    0x20, 0xFA,                      // $004F: jr nz, bottom_row
+    // Turn on the LCD with the logo above the screen and scroll it down
+    0x3E, 0x64,                      // $0051: ld a, 100
+    0xE0, 0x42,                      // $0053: ldh [SCY], a
+    0x3E, 0x91,                      // $0055: ld a, $91
+    0xE0, 0x40,                      // $0057: ldh [LCDC], a
+    0x06, 0x01,                      // $0059: scroll: ld b, 1
+    0xCD, 0xA2, 0x00,                // $005B: call wait_frames
+    0xF0, 0x42,                      // $005E: ldh a, [SCY]
+    0x3D,                            // $0060: dec a
+    0xE0, 0x42,                      // $0061: ldh [SCY], a
+    0x20, 0xF4,                      // $0063: jr nz, scroll
+    // Play the chime and let it ring
+    0x3E, 0x83,                      // $0065: ld a, $83
+    0xCD, 0xB2, 0x00,                // $0067: call play_note
+    0x06, 0x06,                      // $006A: ld b, 6
+    0xCD, 0xA2, 0x00,                // $006C: call wait_frames
+    0x3E, 0xC1,                      // $006F: ld a, $C1
+    0xCD, 0xB2, 0x00,                // $0071: call play_note
+    0x06, 0x3C,                      // $0074: ld b, 60
+    0xCD, 0xA2, 0x00,                // $0076: call wait_frames
+    // Reset DIV and wait, so it has the same value as after the original boot ROM
+    0xAF,                            // $0079: xor a
+    0xE0, 0x04,                      // $007A: ldh [DIV], a
+    0x01, 0x1E, 0x06,                // $007C: ld bc, 1566
+    0x0B,                            // $007F: delay: dec bc
+    0x78,                            // $0080: ld a, b
+    0xB1,                            // $0081: or c
+    0x20, 0xFB,                      // $0082: jr nz, delay
+    0xC3, 0xED, 0x00,                // $0084: jp finish
+    // Writes 4 rows of the logo doubled in size from the byte in A
+    0x4F,                            // $0087: draw_logo_byte: ld c, a
+    0xD5,                            // $0088: push de
+    0x5F,                            // $0089: ld e, a
+    0xCD, 0x92, 0x00,                // $008A: call draw_logo_nibble
+    0xCD, 0x92, 0x00,                // $008D: call draw_logo_nibble
+    0xD1,                            // $0090: pop de
+    0xC9,                            // $0091: ret
+    // Doubles every bit of the upper nibble of C and E, and writes the result twice
+    0x06, 0x04,                      // $0092: draw_logo_nibble: ld b, 4
+    0xCB, 0x11,                      // $0094: double_bits: rl c
+    0x17,                            // $0096: rla
+    0xCB, 0x13,                      // $0097: rl e
+    0x17,                            // $0099: rla
+    0x05,                            // $009A: dec b
+    0x20, 0xF7,                      // $009B: jr nz, double_bits
+    0x22,                            // $009D: ld [hl+], a
+    0x23,                            // $009E: inc hl
+    0x22,                            // $009F: ld [hl+], a
+    0x23,                            // $00A0: inc hl
+    0xC9,                            // $00A1: ret
+    // Waits for the start of VBlank B times
+    0xF0, 0x44,                      // $00A2: wait_frames: ldh a, [LY]
+    0xFE, 0x90,                      // $00A4: cp 144
+    0x20, 0xFA,                      // $00A6: jr nz, wait_frames
+    0xF0, 0x44,                      // $00A8: wait_vblank_end: ldh a, [LY]
+    0xFE, 0x90,                      // $00AA: cp 144
+    0x28, 0xFA,                      // $00AC: jr z, wait_vblank_end
+    0x05,                            // $00AE: dec b
+    0x20, 0xF1,                      // $00AF: jr nz, wait_frames
+    0xC9,                            // $00B1: ret
+    // Plays a note with the lower period bits in A
+    0xE0, 0x13,                      // $00B2: play_note: ldh [NR13], a
+    0x3E, 0x87,                      // $00B4: ld a, $87
+    0xE0, 0x14,                      // $00B6: ldh [NR14], a
+    0xC9,                            // $00B8: ret
+    0x3C, 0x42, 0xB9, 0xA5,          // $00B9: trademark: 8x8 trademark symbol
+    0xB9, 0xA5, 0x42, 0x3C,
+    // Unused
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    // Finish the delay on the exact cycle
+    0x00,                            // $00ED: finish: nop
+    0x00,                            // $00EE: nop
+    0x00,                            // $00EF: nop
+    // Set registers to the values the original boot ROM leaves in them, and unmap the boot ROM
+    0x01, 0xB0, 0x01,                // $00F0: ld bc, $01B0
+    0xC5,                            // $00F3: push bc
+    0xF1,                            // $00F4: pop af
+    0x01, 0x13, 0x00,                // $00F5: ld bc, $0013
+    0x11, 0xD8, 0x00,                // $00F8: ld de, $00D8
+    0x21, 0x4D, 0x01,                // $00FB: ld hl, $014D
+    0xE0, 0x50,                      // $00FE: ldh [BOOT], a
+];
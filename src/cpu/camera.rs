@@ -0,0 +1,208 @@
+use super::*;
+
+/// Width of the image sensor in pixels
+pub const SENSOR_WIDTH: usize = 128;
+/// Height of the image sensor in pixels
+pub const SENSOR_HEIGHT: usize = 112;
+/// Captured image is written as tile data to the start of RAM, after $0100
+const CAPTURE_ADDRESS: usize = 0x0100;
+/// Amount of registers mapped to $A000-$A035
+const REGISTER_AMOUNT: usize = 0x36;
+/// Exposure time at which the brightness of the sensor image is kept as is
+const NEUTRAL_EXPOSURE: i32 = 0x0300;
+/// Edge enhancement ratios selected with bits 4-6 of $A004
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+/// Something that provides images to the Pocket Camera sensor,
+/// like an image file or a generated test pattern
+pub trait ImageSource: Send {
+    /// Returns the next image as `SENSOR_WIDTH` * `SENSOR_HEIGHT` grayscale pixels row by row,
+    /// where 0 is black and 255 is white
+    fn capture(&mut self) -> Vec<u8>;
+}
+
+/// Image source that generates moving bars over a gradient
+pub struct TestPattern {
+    frame: usize,
+}
+
+impl TestPattern {
+    pub fn new() -> Self {
+        Self { frame: 0 }
+    }
+}
+
+impl ImageSource for TestPattern {
+    fn capture(&mut self) -> Vec<u8> {
+        self.frame = self.frame.wrapping_add(1);
+        let mut pixels = Vec::with_capacity(SENSOR_WIDTH * SENSOR_HEIGHT);
+        for y in 0..SENSOR_HEIGHT {
+            for x in 0..SENSOR_WIDTH {
+                let gradient = (x * 255 / (SENSOR_WIDTH - 1)) as u8;
+                // Bars move one pixel every capture
+                let bar = ((x + self.frame) / 16 + y / 28) & 1 == 0;
+                pixels.push(if bar { gradient } else { 255 - gradient });
+            }
+        }
+        pixels
+    }
+}
+
+/// Registers and sensor of the Pocket Camera
+#[derive(Deserialize, Serialize)]
+pub struct Camera {
+    /// If registers are mapped to $A000-$BFFF instead of RAM.
+    /// Selected with bit 4 of the RAM bank register
+    pub registers_mapped: bool,
+    #[serde(with = "BigArray")]
+    registers: [u8; REGISTER_AMOUNT],
+    /// T-cycles left until current capture is finished
+    capture_cycles: u32,
+    /// Source of the sensor image isn't part of the saved state
+    #[serde(skip)]
+    pub source: Option<Box<dyn ImageSource>>,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            registers_mapped: false,
+            registers: [0; REGISTER_AMOUNT],
+            capture_cycles: 0,
+            source: Some(Box::new(TestPattern::new())),
+        }
+    }
+
+    /// Returns value of register at $A000-$BFFF. Registers are mirrored every $80 bytes
+    pub fn read(&self, address: u16) -> u8 {
+        // Only the control register can be read
+        if address & 0x7F == 0 {
+            self.registers[0]
+        } else {
+            0x00
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        let register = (address & 0x7F) as usize;
+        if register >= REGISTER_AMOUNT {
+            return;
+        }
+        if register == 0 {
+            // Capture is started by setting bit 0, and it's cleared once the capture is finished
+            let start = value & 0b1 > 0 && self.capture_cycles == 0;
+            self.registers[0] = value & 0b111;
+            if start {
+                self.capture_cycles = self.capture_length();
+            } else if value & 0b1 == 0 {
+                self.capture_cycles = 0;
+            }
+            return;
+        }
+        self.registers[register] = value;
+    }
+
+    fn exposure(&self) -> u16 {
+        u16::from_be_bytes([self.registers[2], self.registers[3]])
+    }
+
+    /// Returns amount of T-cycles a capture takes with the current exposure time
+    fn capture_length(&self) -> u32 {
+        let n_bit = self.registers[1] & 0b1000_0000 > 0;
+        129792 + if n_bit { 0 } else { 2048 } + self.exposure() as u32 * 64
+    }
+
+    /// Cycles the camera forward by given amount of T-cycles.
+    /// Returns range of RAM that was written to if a capture finished
+    pub fn run(&mut self, cycles: u64, ram: &mut [u8]) -> Option<std::ops::Range<usize>> {
+        if self.capture_cycles == 0 {
+            return None;
+        }
+        if cycles < self.capture_cycles as u64 {
+            self.capture_cycles -= cycles as u32;
+            return None;
+        }
+        self.capture_cycles = 0;
+        self.registers[0] &= !0b1;
+        Some(self.capture(ram))
+    }
+
+    /// Returns the amount of T-cycles until the capture in progress finishes
+    pub fn cycles_until_capture(&self) -> u64 {
+        match self.capture_cycles {
+            0 => u64::MAX,
+            cycles => cycles as u64,
+        }
+    }
+
+    /// Takes an image from the source, processes it and writes it to RAM as tile data
+    fn capture(&mut self, ram: &mut [u8]) -> std::ops::Range<usize> {
+        let mut pixels = match self.source.as_mut() {
+            Some(source) => source.capture(),
+            None => TestPattern::new().capture(),
+        };
+        pixels.resize(SENSOR_WIDTH * SENSOR_HEIGHT, 0);
+
+        let tile_data_size = SENSOR_WIDTH * SENSOR_HEIGHT / 4;
+        let range = CAPTURE_ADDRESS..CAPTURE_ADDRESS + tile_data_size;
+        if ram.len() < range.end {
+            return 0..0;
+        }
+        ram[range.clone()].fill(0);
+        for y in 0..SENSOR_HEIGHT {
+            for x in 0..SENSOR_WIDTH {
+                let shade = self.dither(x, y, self.process(&pixels, x, y));
+                // Image is 16 tiles wide
+                let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+                let index = CAPTURE_ADDRESS + tile * 16 + (y % 8) * 2;
+                let bit = 0b1000_0000 >> (x % 8);
+                if shade & 0b01 > 0 {
+                    ram[index] |= bit;
+                }
+                if shade & 0b10 > 0 {
+                    ram[index + 1] |= bit;
+                }
+            }
+        }
+        range
+    }
+
+    /// Applies the analog processing of the sensor to a pixel
+    fn process(&self, pixels: &[u8], x: usize, y: usize) -> i32 {
+        let pixel = |x: usize, y: usize| {
+            pixels[y.min(SENSOR_HEIGHT - 1) * SENSOR_WIDTH + x.min(SENSOR_WIDTH - 1)] as f32
+        };
+        let mut value = pixel(x, y);
+
+        // Edge enhancement in both directions is selected with bits 5-7 of $A001
+        if self.registers[1] & 0b1110_0000 == 0b1110_0000 {
+            let ratio = EDGE_RATIOS[((self.registers[4] >> 4) & 0b111) as usize];
+            let neighbors = pixel(x.saturating_sub(1), y)
+                + pixel(x + 1, y)
+                + pixel(x, y.saturating_sub(1))
+                + pixel(x, y + 1);
+            value += (value * 4.0 - neighbors) * ratio;
+        }
+        // Output can be inverted with bit 3 of $A004
+        if self.registers[4] & 0b1000 > 0 {
+            value = 255.0 - value;
+        }
+        value as i32 * self.exposure() as i32 / NEUTRAL_EXPOSURE
+    }
+
+    /// Returns the shade of a processed pixel, using the thresholds of the dither matrix
+    fn dither(&self, x: usize, y: usize, value: i32) -> u8 {
+        // Each position of the 4x4 matrix has 3 thresholds starting from $A006
+        let index = 6 + ((y % 4) * 4 + x % 4) * 3;
+        let thresholds = &self.registers[index..index + 3];
+        if value < thresholds[0] as i32 {
+            3
+        } else if value < thresholds[1] as i32 {
+            2
+        } else if value < thresholds[2] as i32 {
+            1
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::*;
+
+/// Speed switch register (KEY1) of the CGB
+#[derive(Deserialize, Serialize)]
+pub struct SpeedSwitch {
+    /// If the system runs in CGB mode. The register doesn't exist in DMG mode
+    pub cgb: bool,
+    /// If CPU and timer run at twice the normal speed
+    pub double_speed: bool,
+    /// Set by the game before executing STOP to switch speed
+    pub armed: bool,
+}
+
+impl SpeedSwitch {
+    pub fn new() -> Self {
+        Self {
+            cgb: false,
+            double_speed: false,
+            armed: false,
+        }
+    }
+}
+
+impl MemoryAccess for SpeedSwitch {
+    fn get_range(&self) -> Vec<RangeInclusive<u16>> {
+        if self.cgb {
+            vec![0xFF4D..=0xFF4D]
+        } else {
+            vec![]
+        }
+    }
+
+    fn mem_read(&self, _address: u16) -> u8 {
+        0x7E | ((self.double_speed as u8) << 7) | self.armed as u8
+    }
+
+    fn mem_write(&mut self, _address: u16, value: u8) {
+        self.armed = value & 0b1 > 0;
+    }
+}
+
+/// VRAM DMA of the CGB, which copies memory to VRAM either all at once
+/// or 16 bytes at a time during HBlank
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+pub struct HDMA {
+    /// If the system runs in CGB mode. Registers don't exist in DMG mode
+    pub cgb: bool,
+    pub source: u16,
+    /// Destination address inside VRAM
+    pub destination: u16,
+    /// Amount of 16 byte blocks left to copy, minus one
+    pub length: u8,
+    /// If a transfer is copying blocks on every HBlank
+    pub hblank_active: bool,
+    /// Set when a general purpose transfer is started, so the CPU copies everything at once
+    pub general_pending: bool,
+}
+
+impl HDMA {
+    pub fn new() -> Self {
+        Self {
+            cgb: false,
+            source: 0,
+            destination: 0,
+            length: 0x7F,
+            hblank_active: false,
+            general_pending: false,
+        }
+    }
+}
+
+impl MemoryAccess for HDMA {
+    fn get_range(&self) -> Vec<RangeInclusive<u16>> {
+        if self.cgb {
+            vec![0xFF51..=0xFF55]
+        } else {
+            vec![]
+        }
+    }
+
+    fn mem_read(&self, address: u16) -> u8 {
+        match address {
+            // Bit 7 is cleared while an HBlank transfer is still going
+            0xFF55 => ((!self.hblank_active as u8) << 7) | self.length,
+            // Source and destination can't be read
+            _ => 0xFF,
+        }
+    }
+
+    fn mem_write(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF51 => self.source = (self.source & 0x00FF) | ((value as u16) << 8),
+            0xFF52 => self.source = (self.source & 0xFF00) | (value & 0xF0) as u16,
+            0xFF53 => {
+                self.destination = (self.destination & 0x00FF) | (((value & 0x1F) as u16) << 8)
+            }
+            0xFF54 => self.destination = (self.destination & 0xFF00) | (value & 0xF0) as u16,
+            0xFF55 => {
+                self.length = value & 0x7F;
+                if value & 0b1000_0000 > 0 {
+                    self.hblank_active = true;
+                } else if self.hblank_active {
+                    // Clearing bit 7 during an HBlank transfer stops it
+                    self.hblank_active = false;
+                } else {
+                    self.general_pending = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl CPU {
+    /// Switches the system to CGB mode and sets the registers
+    /// the CGB boot ROM would leave for the game. Called when the boot ROM is unmapped
+    pub fn enter_cgb_mode(&mut self) {
+        self.ppu.cgb = true;
+        self.mem.cgb = true;
+        self.speed.cgb = true;
+        self.hdma.cgb = true;
+        // Games check for A = $11 to tell that they're running on a CGB
+        self.reg.a = 0x11;
+        self.reg.f = FlagReg::ZERO;
+        self.reg.b = 0x00;
+        self.reg.c = 0x00;
+        self.reg.d = 0xFF;
+        self.reg.e = 0x56;
+        self.reg.h = 0x00;
+        self.reg.l = 0x0D;
+    }
+
+    /// Executes STOP, which switches the CPU speed if the switch has been armed through KEY1
+    pub fn stop(&mut self) {
+        if !self.speed.armed {
+            eprintln!("Tried to STOP");
+            return;
+        }
+        self.speed.armed = false;
+        // Everything is caught up with the old speed, and the events are scheduled again with the new one
+        self.sync_all();
+        self.speed.double_speed = !self.speed.double_speed;
+        self.timer.div = 0;
+        self.sync_all();
+        // CPU is paused for around 2050 M-cycles while the clock settles
+        for _ in 0..32 {
+            self.cycle(64);
+        }
+    }
+
+    /// Copies a block of 16 bytes from the HDMA source to VRAM
+    fn hdma_copy_block(&mut self) {
+        for i in 0..16 {
+            let value = self.read_unrestricted(self.hdma.source.wrapping_add(i));
+            let destination = 0x8000 | ((self.hdma.destination + i) & 0x1FFF);
+            self.ppu.write_vram(destination, value);
+        }
+        self.hdma.source = self.hdma.source.wrapping_add(16);
+        self.hdma.destination = (self.hdma.destination + 16) & 0x1FF0;
+    }
+
+    /// Runs a pending general purpose HDMA transfer, which halts the CPU until it's finished
+    pub fn hdma_general(&mut self) {
+        self.hdma.general_pending = false;
+        let blocks = self.hdma.length as u16 + 1;
+        for _ in 0..blocks {
+            // PPU has to see the old VRAM up to this point
+            self.sync(Component::PPU);
+            self.hdma_copy_block();
+            // Each block takes 8 M-cycles in normal speed and 16 in double speed
+            self.cycle(if self.speed.double_speed { 16 } else { 8 });
+        }
+        self.hdma.length = 0x7F;
+    }
+
+    /// Copies the next block of an HBlank HDMA transfer
+    pub fn hdma_hblank(&mut self) {
+        self.hdma_copy_block();
+        if self.hdma.length == 0 {
+            self.hdma.length = 0x7F;
+            self.hdma.hblank_active = false;
+        } else {
+            self.hdma.length -= 1;
+        }
+    }
+}
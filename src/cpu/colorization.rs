@@ -0,0 +1,291 @@
+use super::*;
+
+/// Background and object palettes that the CGB boot ROM gives to DMG games,
+/// as RGB555 colors from lightest to darkest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompatPalettes {
+    pub bg: [u16; 4],
+    pub obj0: [u16; 4],
+    pub obj1: [u16; 4],
+}
+
+/// Colors of the palettes stored in the boot ROM, 4 per palette
+const PALETTE_COLORS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000, // 0
+    0x639F, 0x4279, 0x15B0, 0x04CB, // 1
+    0x7FFF, 0x6E31, 0x454A, 0x0000, // 2
+    0x7FFF, 0x1BEF, 0x0200, 0x0000, // 3
+    0x7FFF, 0x421F, 0x1CF2, 0x0000, // 4
+    0x7FFF, 0x5294, 0x294A, 0x0000, // 5
+    0x7FFF, 0x03FF, 0x012F, 0x0000, // 6
+    0x7FFF, 0x03EF, 0x01D6, 0x0000, // 7
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000, // 8
+    0x7E74, 0x03FF, 0x0180, 0x0000, // 9
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B, // 10
+    0x7ED6, 0x4BFF, 0x2175, 0x0000, // 11
+    0x53FF, 0x4A5F, 0x7E52, 0x0000, // 12
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0, // 13
+    0x03ED, 0x7FFF, 0x255F, 0x0000, // 14
+    0x036A, 0x021F, 0x03FF, 0x7FFF, // 15
+    0x7FFF, 0x01DF, 0x0112, 0x0000, // 16
+    0x231F, 0x035F, 0x00F2, 0x0009, // 17
+    0x7FFF, 0x03EA, 0x011F, 0x0000, // 18
+    0x299F, 0x001A, 0x000C, 0x0000, // 19
+    0x7FFF, 0x027F, 0x001F, 0x0000, // 20
+    0x7FFF, 0x03E0, 0x0206, 0x0120, // 21
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00, // 22
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F, // 23
+    0x7FFF, 0x03FF, 0x001F, 0x0000, // 24
+    0x03FF, 0x001F, 0x000C, 0x0000, // 25
+    0x7FFF, 0x033F, 0x0193, 0x0000, // 26
+    0x0000, 0x4200, 0x037F, 0x7FFF, // 27
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000, // 28
+    0x7FFF, 0x1BEF, 0x6180, 0x0000, // 29
+];
+
+/// Palette combinations of the boot ROM, as offsets into `PALETTE_COLORS` for OBJ0, OBJ1 and BG.
+/// A few combinations start in the middle of a palette, just like in the boot ROM
+const COMBINATIONS: [[usize; 3]; 51] = [
+    [16, 16, 116],   // 0
+    [72, 72, 72],    // 1
+    [80, 80, 80],    // 2
+    [96, 96, 96],    // 3
+    [36, 36, 36],    // 4
+    [0, 0, 0],       // 5
+    [108, 108, 108], // 6
+    [20, 20, 20],    // 7
+    [48, 48, 48],    // 8
+    [104, 104, 104], // 9
+    [64, 32, 32],    // 10
+    [16, 112, 112],  // 11
+    [16, 8, 8],      // 12
+    [12, 16, 16],    // 13
+    [16, 116, 116],  // 14
+    [112, 16, 112],  // 15
+    [8, 68, 8],      // 16
+    [64, 64, 32],    // 17
+    [16, 16, 28],    // 18
+    [16, 16, 72],    // 19
+    [16, 16, 80],    // 20
+    [76, 76, 36],    // 21
+    [15, 15, 44],    // 22
+    [68, 68, 8],     // 23
+    [16, 16, 8],     // 24
+    [16, 16, 12],    // 25
+    [112, 112, 0],   // 26
+    [12, 12, 0],     // 27
+    [0, 0, 4],       // 28
+    [72, 88, 72],    // 29
+    [80, 88, 80],    // 30
+    [96, 88, 96],    // 31
+    [64, 88, 32],    // 32
+    [68, 16, 52],    // 33
+    [111, 0, 56],    // 34
+    [111, 16, 60],   // 35
+    [76, 88, 36],    // 36
+    [64, 112, 40],   // 37
+    [16, 92, 112],   // 38
+    [68, 88, 8],     // 39
+    [16, 0, 8],      // 40
+    [16, 112, 12],   // 41
+    [112, 12, 0],    // 42
+    [12, 112, 16],   // 43
+    [84, 112, 16],   // 44
+    [12, 112, 0],    // 45
+    [100, 12, 112],  // 46
+    [0, 112, 32],    // 47
+    [16, 12, 112],   // 48
+    [112, 12, 24],   // 49
+    [16, 112, 116],  // 50
+];
+
+/// Returns the palettes of given boot ROM palette combination
+const fn combination(index: usize) -> CompatPalettes {
+    let [obj0, obj1, bg] = COMBINATIONS[index];
+    CompatPalettes {
+        bg: colors(bg),
+        obj0: colors(obj0),
+        obj1: colors(obj1),
+    }
+}
+
+const fn colors(offset: usize) -> [u16; 4] {
+    [
+        PALETTE_COLORS[offset],
+        PALETTE_COLORS[offset + 1],
+        PALETTE_COLORS[offset + 2],
+        PALETTE_COLORS[offset + 3],
+    ]
+}
+
+/// Palettes that can be picked by holding a button combination during the CGB boot animation
+pub const BUTTON_PALETTES: [(&str, CompatPalettes); 12] = [
+    ("Up", combination(5)),
+    ("Up+A", combination(43)),
+    ("Up+B", combination(28)),
+    ("Left", combination(48)),
+    ("Left+A", combination(40)),
+    ("Left+B", combination(7)),
+    ("Down", combination(8)),
+    ("Down+A", combination(3)),
+    ("Down+B", combination(49)),
+    ("Right", combination(0)),
+    ("Right+A", combination(1)),
+    ("Right+B", combination(6)),
+];
+
+/// Palettes of games that aren't in the lookup table
+pub const DEFAULT_PALETTES: CompatPalettes = combination(0);
+
+/// Games recognized by the boot ROM, with the title checksum,
+/// the 4th letter of the title for checksums shared by several games, and the palette combination.
+/// Entries are checked in order, so the ones with a letter come after the unique checksums
+const GAME_PALETTES: [(u8, Option<u8>, usize); 94] = [
+    (0x00, None, 0),
+    (0x88, None, 4),  // ALLEY WAY
+    (0x16, None, 5),  // YAKUMAN
+    (0x36, None, 35), // BASEBALL
+    (0xD1, None, 34), // TENNIS
+    (0xDB, None, 3),  // TETRIS
+    (0xF2, None, 31), // QIX
+    (0x3C, None, 15), // DR.MARIO
+    (0x8C, None, 10), // RADARMISSION
+    (0x92, None, 5),  // F1RACE
+    (0x3D, None, 19), // YOSSY NO TAMAGO
+    (0x5C, None, 36),
+    (0x58, None, 7),  // X
+    (0xC9, None, 37), // MARIOLAND2
+    (0x3E, None, 30), // YOSSY NO COOKIE
+    (0x70, None, 44), // ZELDA
+    (0x1D, None, 21),
+    (0x59, None, 32),
+    (0x69, None, 31), // TETRIS FLASH
+    (0x19, None, 20), // DONKEY KONG
+    (0x35, None, 5),  // MARIO'S PICROSS
+    (0xA8, None, 33),
+    (0x14, None, 13), // POKEMON RED
+    (0xAA, None, 14), // POKEMON GREEN
+    (0x75, None, 5),  // PICROSS 2
+    (0x95, None, 29), // YOSSY NO PANEPON
+    (0x99, None, 5),  // KIRAKIRA KIDS
+    (0x34, None, 18), // GAMEBOY GALLERY
+    (0x6F, None, 9),  // POCKETCAMERA
+    (0x15, None, 3),
+    (0xFF, None, 2),  // BALLOON KID
+    (0x97, None, 26), // KINGOFTHEZOO
+    (0x4B, None, 25), // DMG FOOTBALL
+    (0x90, None, 25), // WORLD CUP
+    (0x17, None, 41), // OTHELLO
+    (0x10, None, 42), // SUPER RC PRO-AM
+    (0x39, None, 26), // DYNABLASTER
+    (0xF7, None, 45), // BOY AND BLOB GB2
+    (0xF6, None, 42), // MEGAMAN
+    (0xA2, None, 45), // STAR WARS-NOA
+    (0x49, None, 36),
+    (0x4E, None, 38), // WAVERACE
+    (0x43, None, 26),
+    (0x68, None, 42), // LOLO2
+    (0xE0, None, 30), // YOSHI'S COOKIE
+    (0x8B, None, 41), // MYSTIC QUEST
+    (0xF0, None, 34),
+    (0xCE, None, 34), // TOPRANKINGTENNIS
+    (0x0C, None, 5),  // MANSELL
+    (0x29, None, 42), // MEGAMAN3
+    (0xE8, None, 6),  // SPACE INVADERS
+    (0xB7, None, 5),  // GAME&WATCH
+    (0x86, None, 33), // DONKEYKONGLAND95
+    (0x9A, None, 25), // ASTEROIDS/MISCMD
+    (0x52, None, 42), // STREET FIGHTER 2
+    (0x01, None, 42), // DEFENDER/JOUST
+    (0x9D, None, 40), // KILLERINSTINCT95
+    (0x71, None, 2),  // TETRIS BLAST
+    (0x9C, None, 16), // PINOCCHIO
+    (0xBD, None, 25),
+    (0x5D, None, 42), // BA.TOSHINDEN
+    (0x6D, None, 42), // NETTOU KOF 95
+    (0x67, None, 5),
+    (0x3F, None, 0),  // TETRIS PLUS
+    (0x6B, None, 39), // DONKEYKONGLAND 3
+    (0xB3, Some(b'B'), 36),
+    (0x46, Some(b'E'), 22), // SUPER MARIOLAND
+    (0x28, Some(b'F'), 25), // GOLF
+    (0xA5, Some(b'A'), 6),  // SOLARSTRIKER
+    (0xC6, Some(b'A'), 32), // GBWARS
+    (0xD3, Some(b'R'), 12), // KAERUNOTAMENI
+    (0x27, Some(b'B'), 36),
+    (0x61, Some(b'E'), 11), // POKEMON BLUE
+    (0x18, Some(b'K'), 39), // DONKEYKONGLAND
+    (0x66, Some(b'E'), 18), // GAMEBOY GALLERY2
+    (0x6A, Some(b'K'), 39), // DONKEYKONGLAND 2
+    (0xBF, Some(b' '), 24), // KID ICARUS
+    (0x0D, Some(b'R'), 31), // TETRIS2
+    (0xF4, Some(b'-'), 50),
+    (0xB3, Some(b'U'), 17), // MOGURANYA
+    (0x46, Some(b'R'), 46),
+    (0x28, Some(b'A'), 6),
+    (0xA5, Some(b'R'), 27), // BT2RAGNAROKWORLD
+    (0xC6, Some(b' '), 0),  // KEN GRIFFEY JR
+    (0xD3, Some(b'I'), 47),
+    (0x27, Some(b'N'), 41), // MAGNETIC SOCCER
+    (0x61, Some(b'A'), 41), // VEGAS STAKES
+    (0x18, Some(b'I'), 0),
+    (0x66, Some(b'L'), 0),  // MILLI/CENTI/PEDE
+    (0x6A, Some(b'I'), 19), // MARIO & YOSHI
+    (0xBF, Some(b'C'), 34), // SOCCER
+    (0x0D, Some(b'E'), 23), // POKEBOM
+    (0xF4, Some(b' '), 18), // G&W GALLERY
+    (0xB3, Some(b'R'), 29), // TETRIS ATTACK
+];
+
+/// How DMG games are colored
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Colorization {
+    /// Games are drawn with the 2-bit shades, which the frontend colors itself
+    #[default]
+    Off,
+    /// Palettes are looked up from the title checksum like the CGB boot ROM does
+    Auto,
+    /// One of the button combination palettes, indexing `BUTTON_PALETTES`
+    Preset(usize),
+}
+
+impl Colorization {
+    /// Returns the palettes the given DMG game should be colored with
+    pub fn palettes(&self, rom: &[u8]) -> Option<CompatPalettes> {
+        match self {
+            Colorization::Off => None,
+            Colorization::Auto => Some(lookup_palettes(rom)),
+            Colorization::Preset(index) => {
+                BUTTON_PALETTES.get(*index).map(|(_, palettes)| *palettes)
+            }
+        }
+    }
+}
+
+/// Returns the sum of the title bytes, if the game was published by Nintendo.
+/// The boot ROM only recognizes Nintendo's own games
+pub fn title_checksum(rom: &[u8]) -> Option<u8> {
+    let old_licensee = rom[0x014B];
+    let nintendo = old_licensee == 0x01 || (old_licensee == 0x33 && &rom[0x0144..=0x0145] == b"01");
+    if !nintendo {
+        return None;
+    }
+    Some(
+        rom[0x0134..=0x0143]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte)),
+    )
+}
+
+/// Looks up the palettes of a game from its title checksum
+pub fn lookup_palettes(rom: &[u8]) -> CompatPalettes {
+    let Some(checksum) = title_checksum(rom) else {
+        return DEFAULT_PALETTES;
+    };
+    GAME_PALETTES
+        .iter()
+        .find(|(game_checksum, letter, _)| {
+            *game_checksum == checksum && letter.is_none_or(|letter| rom[0x0137] == letter)
+        })
+        .map_or(DEFAULT_PALETTES, |(_, _, index)| combination(*index))
+}
@@ -0,0 +1,190 @@
+use super::*;
+
+/// Size of the HuC3 clock state that is appended to the end of save files
+pub const HUC3_FOOTER_SIZE: usize = 16;
+/// Clock counts minutes, and doesn't expose seconds at all
+const CYCLES_PER_MINUTE: u32 = 4194304 * 60;
+const MINUTES_PER_DAY: u16 = 1440;
+
+/// Which register is mapped to $A000-$BFFF, selected by writing to $0000-$1FFF
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum HuC3Mode {
+    /// RAM can only be read
+    RAMReadOnly,
+    RAM,
+    /// Commands to the clock are written here
+    Command,
+    /// Result of the previous command is read here
+    Response,
+    /// Tells if the clock is ready to receive a command
+    Semaphore,
+    /// Infrared port
+    IR,
+}
+
+impl HuC3Mode {
+    pub fn from_value(value: u8) -> Self {
+        match value & 0x0F {
+            0x0A => HuC3Mode::RAM,
+            0x0B => HuC3Mode::Command,
+            0x0C => HuC3Mode::Response,
+            0x0D => HuC3Mode::Semaphore,
+            0x0E => HuC3Mode::IR,
+            _ => HuC3Mode::RAMReadOnly,
+        }
+    }
+}
+
+/// Real-time clock of HuC3 cartridges, which is controlled with 4-bit commands.
+/// The clock is accessed through nibble-addressed memory,
+/// where the minute counter is at $00-$02 and the day counter at $03-$06
+#[derive(Deserialize, Serialize)]
+pub struct HuC3 {
+    pub mode: HuC3Mode,
+    /// Minutes since the start of the current day
+    minutes: u16,
+    days: u16,
+    /// T-cycles since the clock last ticked
+    cycles: u32,
+    /// Rest of the clock memory, which holds the alarm and other settings
+    #[serde(with = "BigArray")]
+    memory: [u8; 0x100],
+    /// Memory address that is accessed by the next read or write command
+    address: u8,
+    /// Value that is read in response mode
+    response: u8,
+}
+
+impl HuC3 {
+    pub fn new() -> Self {
+        Self {
+            mode: HuC3Mode::RAMReadOnly,
+            minutes: 0,
+            days: 0,
+            cycles: 0,
+            memory: [0; 0x100],
+            address: 0,
+            response: 0,
+        }
+    }
+
+    /// Cycles the clock forward by given amount of T-cycles.
+    /// Returns true if the clock ticked
+    pub fn run(&mut self, cycles: u64) -> bool {
+        let total = self.cycles as u64 + cycles;
+        self.cycles = (total % CYCLES_PER_MINUTE as u64) as u32;
+        let minutes = total / CYCLES_PER_MINUTE as u64;
+        self.advance(minutes);
+        minutes > 0
+    }
+
+    /// Returns the amount of T-cycles until the clock ticks
+    pub fn cycles_until_tick(&self) -> u64 {
+        (CYCLES_PER_MINUTE - self.cycles) as u64
+    }
+
+    /// Advances the clock by given amount of minutes
+    fn advance(&mut self, minutes: u64) {
+        let total = self.minutes as u64 + minutes;
+        self.minutes = (total % MINUTES_PER_DAY as u64) as u16;
+        self.days = self
+            .days
+            .wrapping_add((total / MINUTES_PER_DAY as u64) as u16);
+    }
+
+    /// Returns value of the register selected by the current mode
+    pub fn read(&self) -> u8 {
+        match self.mode {
+            HuC3Mode::Response => self.response,
+            // Clock is always ready, since commands finish instantly
+            HuC3Mode::Semaphore => 0x01,
+            // No infrared light is ever received
+            HuC3Mode::IR => 0xC0,
+            _ => 0xFF,
+        }
+    }
+
+    /// Writes to the register selected by the current mode.
+    /// Returns true if the clock was changed
+    pub fn write(&mut self, value: u8) -> bool {
+        if self.mode != HuC3Mode::Command {
+            return false;
+        }
+        let command = (value >> 4) & 0b111;
+        let argument = value & 0x0F;
+        let mut changed = false;
+        match command {
+            // Read value and increment address
+            0x1 => {
+                self.response = (command << 4) | self.read_nibble(self.address);
+                self.address = self.address.wrapping_add(1);
+            }
+            // Write value, and increment address with command 3
+            0x2 | 0x3 => {
+                self.write_nibble(self.address, argument);
+                if command == 0x3 {
+                    self.address = self.address.wrapping_add(1);
+                }
+                changed = true;
+            }
+            // Set lower and upper nibble of the address
+            0x4 => self.address = (self.address & 0xF0) | argument,
+            0x5 => self.address = (self.address & 0x0F) | (argument << 4),
+            // Extended commands. Only the status check has a response
+            0x6 if argument == 0x2 => self.response = (command << 4) | 0x1,
+            _ => {}
+        }
+        changed
+    }
+
+    fn read_nibble(&self, address: u8) -> u8 {
+        match address {
+            0x00..=0x02 => ((self.minutes >> (address * 4)) & 0x0F) as u8,
+            0x03..=0x06 => ((self.days >> ((address - 3) * 4)) & 0x0F) as u8,
+            _ => self.memory[address as usize],
+        }
+    }
+
+    fn write_nibble(&mut self, address: u8, value: u8) {
+        match address {
+            0x00..=0x02 => {
+                let shift = address * 4;
+                self.minutes = (self.minutes & !(0x0F << shift)) | ((value as u16) << shift);
+                // Writing the minutes resets the counter that counts towards the next minute
+                self.cycles = 0;
+            }
+            0x03..=0x06 => {
+                let shift = (address - 3) * 4;
+                self.days = (self.days & !(0x0F << shift)) | ((value as u16) << shift);
+            }
+            _ => self.memory[address as usize] = value,
+        }
+    }
+
+    /// Returns clock state as a save file footer, with current time as the timestamp
+    pub fn to_footer(&self) -> [u8; HUC3_FOOTER_SIZE] {
+        let mut footer = [0; HUC3_FOOTER_SIZE];
+        footer[0..4].copy_from_slice(&(self.minutes as u32).to_le_bytes());
+        footer[4..8].copy_from_slice(&(self.days as u32).to_le_bytes());
+        footer[8..16].copy_from_slice(&unix_time().to_le_bytes());
+        footer
+    }
+
+    /// Loads clock state from a save file footer,
+    /// and advances the clock by the time that has passed since it was saved
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        if footer.len() < HUC3_FOOTER_SIZE {
+            return;
+        }
+        let minutes = u32::from_le_bytes(footer[0..4].try_into().unwrap());
+        let days = u32::from_le_bytes(footer[4..8].try_into().unwrap());
+        self.minutes = (minutes % MINUTES_PER_DAY as u32) as u16;
+        self.days = days as u16;
+
+        let timestamp = u64::from_le_bytes(footer[8..16].try_into().unwrap());
+        // Timestamp is 0 if the footer was just added to an older save file
+        if timestamp != 0 {
+            self.advance(unix_time().saturating_sub(timestamp) / 60);
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use super::*;
 
 #[derive(Deserialize, Serialize, Clone, Copy, Hash, Eq, PartialEq)]
-pub struct InputFlag(u8);
+pub struct InputFlag(u16);
 
 bitflags! {
-    impl InputFlag: u8 {
+    impl InputFlag: u16 {
         const START  = 0b1000_0000;
         const SELECT = 0b0100_0000;
         const B      = 0b0010_0000;
@@ -13,6 +13,11 @@ bitflags! {
         const UP     = 0b0000_0100;
         const LEFT   = 0b0000_0010;
         const RIGHT  = 0b0000_0001;
+        // Tilt directions are only read by MBC7 cartridges
+        const TILT_RIGHT = 0b0001_0000_0000;
+        const TILT_LEFT  = 0b0010_0000_0000;
+        const TILT_UP    = 0b0100_0000_0000;
+        const TILT_DOWN  = 0b1000_0000_0000;
     }
 }
 
@@ -21,6 +26,22 @@ pub struct InputReg {
     pub select_button: bool,
     pub select_dpad: bool,
     pub flags: InputFlag,
+    /// If a Super Game Boy is listening for packets on the joypad lines
+    pub sgb: bool,
+    /// Buttons of players 2-4 when the Super Game Boy has multiplayer enabled
+    pub other_players: [InputFlag; 3],
+    /// Amount of players enabled with MLT_REQ
+    pub players: u8,
+    /// Player whose buttons are currently read
+    pub player: u8,
+    /// Packet that is currently being received
+    packet: [u8; 16],
+    /// Index of the next packet bit, or None if no packet is being received
+    packet_bit: Option<u8>,
+    /// State of the P14 and P15 lines on the previous write
+    previous_lines: u8,
+    /// Set once a whole packet has been received, so the Super Game Boy can handle it
+    pub sgb_packet: Option<[u8; 16]>,
 }
 
 impl InputReg {
@@ -28,7 +49,63 @@ impl InputReg {
         Self {
             select_button: false,
             select_dpad: false,
-            flags: InputFlag::from_bits_truncate(0xFF),
+            flags: InputFlag::all(),
+            sgb: false,
+            other_players: [InputFlag::all(); 3],
+            players: 1,
+            player: 0,
+            packet: [0; 16],
+            packet_bit: None,
+            previous_lines: 0x30,
+            sgb_packet: None,
+        }
+    }
+
+    /// Returns the buttons of the player that is currently read
+    fn player_flags(&self) -> InputFlag {
+        match self.player {
+            0 => self.flags,
+            player => self.other_players[player as usize - 1],
+        }
+    }
+
+    /// Sets the amount of players after MLT_REQ, starting from player 1
+    pub fn set_players(&mut self, players: u8) {
+        self.players = players;
+        self.player = 0;
+    }
+
+    /// Super Game Boy reads packets from pulses on the P14 and P15 lines.
+    /// Pulling both low starts a packet, after which pulling P14 low sends a 0 and P15 a 1.
+    /// Lines are released between bits, and 128 bits are followed by a 0 stop bit
+    fn pulse(&mut self, lines: u8) {
+        match lines {
+            0x00 => {
+                self.packet = [0; 16];
+                self.packet_bit = Some(0);
+            }
+            0x10 | 0x20 if self.previous_lines == 0x30 => {
+                let Some(index) = self.packet_bit else {
+                    return;
+                };
+                if index == 128 {
+                    self.packet_bit = None;
+                    // A 1 in place of the stop bit cancels the packet
+                    if lines == 0x20 {
+                        self.sgb_packet = Some(self.packet);
+                    }
+                    return;
+                }
+                if lines == 0x10 {
+                    self.packet[index as usize / 8] |= 1 << (index % 8);
+                }
+                self.packet_bit = Some(index + 1);
+            }
+            // Releasing P15 switches to the next player
+            0x30 if self.previous_lines & 0x20 == 0 && self.players > 1 => {
+                self.player = (self.player + 1) & (self.players - 1);
+            }
+            _ => {}
         }
     }
 
@@ -38,7 +115,7 @@ impl InputReg {
         let pressed = !input.bits() & self.flags.bits();
         // Mask out pressed button based on selected input type
         let send_interrupt = if self.select_button {
-            (pressed >> 4) > 0
+            (pressed >> 4) & 0x0F > 0
         } else if self.select_dpad {
             pressed & 0x0F > 0
         } else {
@@ -58,12 +135,14 @@ impl MemoryAccess for InputReg {
     fn mem_read(&self, _: u16) -> u8 {
         let select_bits = ((self.select_button as u8) << 5) | ((self.select_dpad as u8) << 4);
 
+        let flags = self.player_flags();
         let input = if self.select_button {
-            (self.flags.bits() & 0xF0) >> 4
+            ((flags.bits() & 0xF0) >> 4) as u8
         } else if self.select_dpad {
-            self.flags.bits() & 0x0F
+            (flags.bits() & 0x0F) as u8
         } else {
-            0x0F
+            // With multiplayer, the Super Game Boy tells which player is read
+            0x0F - self.player
         };
 
         select_bits | input
@@ -72,5 +151,10 @@ impl MemoryAccess for InputReg {
     fn mem_write(&mut self, _: u16, value: u8) {
         self.select_button = value & 0b0010_0000 == 0;
         self.select_dpad = value & 0b0001_0000 == 0;
+        if self.sgb {
+            let lines = value & 0x30;
+            self.pulse(lines);
+            self.previous_lines = lines;
+        }
     }
 }
@@ -61,6 +61,14 @@ impl CPU {
         if self.input.update(*input) {
             self.request_interrupt(InterruptFlag::JOYPAD);
         }
+        // Pass tilt to the accelerometer. Input flags use 0 to mark a pressed button
+        let axis = |positive, negative| {
+            (!input.contains(positive) as i8 - !input.contains(negative) as i8) as f32
+        };
+        self.mem.mbc.mbc7.tilt = (
+            axis(InputFlag::TILT_RIGHT, InputFlag::TILT_LEFT),
+            axis(InputFlag::TILT_DOWN, InputFlag::TILT_UP),
+        );
     }
 
     /// Sets corresponding interrupt flag to true
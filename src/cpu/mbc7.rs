@@ -0,0 +1,288 @@
+use super::*;
+
+/// Accelerometer value when the Game Boy is held flat
+const ACCELEROMETER_CENTER: u16 = 0x81D0;
+/// Change in accelerometer value when the Game Boy is fully tilted
+const ACCELEROMETER_TILT: u16 = 0x70;
+
+/// What the EEPROM expects to receive next
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum EEPROMState {
+    /// Waiting for a start bit
+    Idle,
+    /// Receiving the 2-bit opcode and 8-bit address of a command
+    Command,
+    /// Shifting out a 16-bit word
+    Read { data: u16, bits_left: u8 },
+    /// Receiving a 16-bit word to write. Written to all words if address is None
+    Write {
+        address: Option<u8>,
+        data: u16,
+        bits: u8,
+    },
+}
+
+/// 93LC56 serial EEPROM, organized as 128 16-bit words.
+/// The contents are stored in cartridge RAM so they're saved like battery RAM
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+struct EEPROM {
+    /// Chip select
+    cs: bool,
+    /// Serial clock
+    clk: bool,
+    /// Data input
+    di: bool,
+    /// Data output
+    do_bit: bool,
+    state: EEPROMState,
+    command: u16,
+    command_bits: u8,
+    /// Writing and erasing is disabled until enabled with the EWEN command
+    write_enabled: bool,
+}
+
+impl EEPROM {
+    fn new() -> Self {
+        Self {
+            cs: false,
+            clk: false,
+            di: false,
+            do_bit: true,
+            state: EEPROMState::Idle,
+            command: 0,
+            command_bits: 0,
+            write_enabled: false,
+        }
+    }
+
+    fn read(&self) -> u8 {
+        ((self.cs as u8) << 7)
+            | ((self.clk as u8) << 6)
+            | ((self.di as u8) << 1)
+            | self.do_bit as u8
+    }
+
+    /// Updates the pins of the chip.
+    /// Returns the index of the word that was written to, or None if nothing was written
+    fn write(&mut self, value: u8, memory: &mut [u8]) -> Option<WrittenWords> {
+        let cs = value & 0b1000_0000 > 0;
+        let clk = value & 0b0100_0000 > 0;
+        self.di = value & 0b0000_0010 > 0;
+
+        let mut written = None;
+        if !cs {
+            // Deselecting the chip cancels the current command
+            self.state = EEPROMState::Idle;
+        } else if clk && !self.clk {
+            // Data is shifted on the rising edge of the clock
+            written = self.shift(memory);
+        }
+        self.cs = cs;
+        self.clk = clk;
+        written
+    }
+
+    fn shift(&mut self, memory: &mut [u8]) -> Option<WrittenWords> {
+        match self.state {
+            EEPROMState::Idle => {
+                if self.di {
+                    self.state = EEPROMState::Command;
+                    self.command = 0;
+                    self.command_bits = 0;
+                }
+            }
+            EEPROMState::Command => {
+                self.command = (self.command << 1) | self.di as u16;
+                self.command_bits += 1;
+                if self.command_bits == 10 {
+                    return self.run_command(memory);
+                }
+            }
+            EEPROMState::Read { data, bits_left } => {
+                self.do_bit = data & 0x8000 > 0;
+                self.state = if bits_left > 1 {
+                    EEPROMState::Read {
+                        data: data << 1,
+                        bits_left: bits_left - 1,
+                    }
+                } else {
+                    EEPROMState::Idle
+                };
+            }
+            EEPROMState::Write {
+                address,
+                data,
+                bits,
+            } => {
+                let data = (data << 1) | self.di as u16;
+                if bits < 15 {
+                    self.state = EEPROMState::Write {
+                        address,
+                        data,
+                        bits: bits + 1,
+                    };
+                    return None;
+                }
+                self.state = EEPROMState::Idle;
+                // Writes finish instantly, so the chip always reports being ready
+                self.do_bit = true;
+                if !self.write_enabled {
+                    return None;
+                }
+                return Some(match address {
+                    Some(address) => {
+                        write_word(memory, address, data);
+                        WrittenWords::One(address)
+                    }
+                    None => {
+                        (0..128).for_each(|address| write_word(memory, address, data));
+                        WrittenWords::All
+                    }
+                });
+            }
+        }
+        None
+    }
+
+    fn run_command(&mut self, memory: &mut [u8]) -> Option<WrittenWords> {
+        let address = (self.command & 0x7F) as u8;
+        self.state = EEPROMState::Idle;
+        match self.command >> 8 {
+            // READ
+            0b10 => {
+                // A dummy 0 bit is sent before the data
+                self.do_bit = false;
+                self.state = EEPROMState::Read {
+                    data: read_word(memory, address),
+                    bits_left: 16,
+                };
+            }
+            // WRITE
+            0b01 => {
+                self.state = EEPROMState::Write {
+                    address: Some(address),
+                    data: 0,
+                    bits: 0,
+                }
+            }
+            // ERASE
+            0b11 => {
+                self.do_bit = true;
+                if self.write_enabled {
+                    write_word(memory, address, 0xFFFF);
+                    return Some(WrittenWords::One(address));
+                }
+            }
+            // Rest of the commands are selected with the upper address bits
+            _ => match (self.command >> 6) & 0b11 {
+                // EWDS
+                0b00 => self.write_enabled = false,
+                // WRAL
+                0b01 => {
+                    self.state = EEPROMState::Write {
+                        address: None,
+                        data: 0,
+                        bits: 0,
+                    }
+                }
+                // ERAL
+                0b10 => {
+                    self.do_bit = true;
+                    if self.write_enabled {
+                        (0..128).for_each(|address| write_word(memory, address, 0xFFFF));
+                        return Some(WrittenWords::All);
+                    }
+                }
+                // EWEN
+                _ => self.write_enabled = true,
+            },
+        }
+        None
+    }
+}
+
+/// Words of the EEPROM that were changed by a command
+pub enum WrittenWords {
+    One(u8),
+    All,
+}
+
+fn read_word(memory: &[u8], address: u8) -> u16 {
+    let index = address as usize * 2;
+    u16::from_le_bytes([memory[index], memory[index + 1]])
+}
+
+fn write_word(memory: &mut [u8], address: u8, data: u16) {
+    let index = address as usize * 2;
+    memory[index..index + 2].copy_from_slice(&data.to_le_bytes());
+}
+
+/// Accelerometer and EEPROM of MBC7 cartridges
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+pub struct MBC7 {
+    /// Registers are only accessible if this and the regular RAM enable are both set
+    pub registers_enabled: bool,
+    /// Current tilt of the Game Boy on both axes, from -1.0 to 1.0.
+    /// Positive values mean tilting right and down
+    pub tilt: (f32, f32),
+    accelerometer_x: u16,
+    accelerometer_y: u16,
+    /// Accelerometer can only be latched after the latched values have been erased
+    latch_ready: bool,
+    eeprom: EEPROM,
+}
+
+impl MBC7 {
+    pub fn new() -> Self {
+        Self {
+            registers_enabled: false,
+            tilt: (0.0, 0.0),
+            accelerometer_x: 0x8000,
+            accelerometer_y: 0x8000,
+            latch_ready: false,
+            eeprom: EEPROM::new(),
+        }
+    }
+
+    /// Returns value of register at $A000-$AFFF, which is selected with address bits 4-7
+    pub fn read(&self, address: u16) -> u8 {
+        match (address >> 4) & 0x0F {
+            0x2 => (self.accelerometer_x & 0xFF) as u8,
+            0x3 => (self.accelerometer_x >> 8) as u8,
+            0x4 => (self.accelerometer_y & 0xFF) as u8,
+            0x5 => (self.accelerometer_y >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    /// Writes to register at $A000-$AFFF.
+    /// Returns words of the EEPROM that were written to
+    pub fn write(&mut self, address: u16, value: u8, eeprom: &mut [u8]) -> Option<WrittenWords> {
+        match (address >> 4) & 0x0F {
+            // Erase latched values
+            0x0 if value == 0x55 => {
+                self.accelerometer_x = 0x8000;
+                self.accelerometer_y = 0x8000;
+                self.latch_ready = true;
+            }
+            // Latch current accelerometer values
+            // Tilting right or down decreases the value
+            0x1 if value == 0xAA && self.latch_ready => {
+                self.accelerometer_x = Self::accelerometer_value(-self.tilt.0);
+                self.accelerometer_y = Self::accelerometer_value(-self.tilt.1);
+                self.latch_ready = false;
+            }
+            0x8 => return self.eeprom.write(value, eeprom),
+            _ => {}
+        }
+        None
+    }
+
+    fn accelerometer_value(tilt: f32) -> u16 {
+        (ACCELEROMETER_CENTER as f32 + tilt.clamp(-1.0, 1.0) * ACCELEROMETER_TILT as f32) as u16
+    }
+}
@@ -5,14 +5,28 @@ use memmap2::MmapMut;
 pub enum MBCType {
     NoMBC,
     MBC1,
+    /// MBC1 multicart, which has the bank registers wired for 256 KiB games
+    MBC1M,
     MBC2,
     MMM01,
     MBC3,
     MBC5,
     MBC6,
     MBC7,
+    HuC1,
+    HuC3,
+    PocketCamera,
+    /// Cartridge type byte that isn't known
+    Unknown(u8),
 }
 
+/// Logo that every cartridge header has to contain at $0104
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
 #[derive(Deserialize, Serialize, Debug, Clone, Copy)]
 pub struct CartridgeInfo {
     /// Type of memory bank controller
@@ -26,9 +40,55 @@ pub struct CartridgeInfo {
     pub rom_banks: u16,
     /// Amount of 8 KiB RAM banks cartridge provides
     pub ram_banks: u16,
+    /// If cartridge has a rumble motor
+    pub has_rumble: bool,
+    /// If cartridge has a real-time clock
+    pub has_rtc: bool,
+    /// If game supports the CGB, and should be run in CGB mode
+    pub cgb: bool,
+    /// If game supports the Super Game Boy
+    pub sgb: bool,
 }
 
 impl CartridgeInfo {
+    /// Returns info about cartridge features from the ROM file.
+    /// Unlike `from_header`, also detects multicarts whose first header doesn't describe the cartridge
+    pub fn from_rom(rom: &[u8]) -> Self {
+        // MMM01 cartridges boot into a menu at the end of the ROM, which has the real header
+        if rom.len() >= 0x8000 {
+            let menu_header = &rom[rom.len() - 0x8000 + 0x0100..=rom.len() - 0x8000 + 0x014F];
+            if matches!(menu_header[0x47], 0x0B..=0x0D) && Self::is_valid_header(menu_header) {
+                let mut info = Self::from_header(menu_header);
+                // Menu header doesn't always describe the whole cartridge
+                info.rom_banks = (rom.len() / 0x4000).next_power_of_two() as u16;
+                return info;
+            }
+        }
+        let mut info = Self::from_header(&rom[0x0100..=0x014F]);
+        if matches!(info.mbc, MBCType::MBC1) && Self::is_mbc1_multicart(rom) {
+            info.mbc = MBCType::MBC1M;
+        }
+        info
+    }
+
+    /// MBC1 multicarts are 1 MiB and hold a game every 256 KiB.
+    /// They can be detected from the Nintendo logo of the second game's header
+    fn is_mbc1_multicart(rom: &[u8]) -> bool {
+        let logo = 0x0104..0x0134;
+        let second_game = 0x10 * 0x4000;
+        rom.len() == 0x40 * 0x4000
+            && rom[logo.clone()] == rom[second_game + logo.start..second_game + logo.end]
+    }
+
+    /// Checks the Nintendo logo and the header checksum like the boot ROM does,
+    /// so random data isn't mistaken for a header
+    fn is_valid_header(header: &[u8]) -> bool {
+        let checksum = header[0x34..=0x4C]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        header[0x04..0x34] == NINTENDO_LOGO && header[0x4D] == checksum
+    }
+
     /// Returns info about cartridge features from the ROM header
     pub fn from_header(header: &[u8]) -> Self {
         let mbc = match header[0x47] {
@@ -39,16 +99,41 @@ impl CartridgeInfo {
             0x19..=0x1E => MBCType::MBC5,
             0x20 => MBCType::MBC6,
             0x22 => MBCType::MBC7,
-            _ => MBCType::NoMBC,
+            0xFC => MBCType::PocketCamera,
+            0xFE => MBCType::HuC3,
+            0xFF => MBCType::HuC1,
+            0x00 | 0x08 | 0x09 => MBCType::NoMBC,
+            byte => MBCType::Unknown(byte),
         };
         let has_ram = matches!(
             header[0x47],
-            0x02 | 0x03 | 0x0C | 0x0D | 0x10 | 0x12 | 0x13 | 0x1A | 0x1B | 0x1D | 0x1E | 0x22
+            0x02 | 0x03
+                | 0x05
+                | 0x06
+                | 0x0C
+                | 0x0D
+                | 0x10
+                | 0x12
+                | 0x13
+                | 0x1A
+                | 0x1B
+                | 0x1D
+                | 0x1E
+                | 0x22
+                | 0xFC
+                | 0xFE
+                | 0xFF
         );
         let has_battery = matches!(
             header[0x47],
-            0x03 | 0x06 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22
+            0x03 | 0x06 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFC | 0xFE | 0xFF
         );
+        let has_rumble = matches!(header[0x47], 0x1C..=0x1E);
+        let has_rtc = matches!(header[0x47], 0x0F | 0x10 | 0xFE);
+        // Both CGB enhanced ($80) and CGB only ($C0) games set bit 7
+        let cgb = header[0x43] & 0x80 > 0;
+        // Super Game Boy features are only enabled with the new licensee code
+        let sgb = header[0x46] == 0x03 && header[0x4B] == 0x33;
         let rom_banks = 2u16.pow((1 + header[0x48]) as u32);
         let ram_banks = if !has_ram {
             0
@@ -67,45 +152,111 @@ impl CartridgeInfo {
             has_battery,
             rom_banks,
             ram_banks,
+            has_rumble,
+            has_rtc,
+            cgb,
+            sgb,
         }
     }
+
+    /// Returns size of external RAM in bytes
+    pub fn ram_size(&self) -> usize {
+        match self.mbc {
+            // MBC2 has 512 half-bytes of RAM built in
+            MBCType::MBC2 => 0x200,
+            // MBC7 has a 256 byte EEPROM instead of RAM
+            MBCType::MBC7 => 0x100,
+            _ => 0x2000 * usize::from(self.ram_banks),
+        }
+    }
+
+    /// Returns size of the RTC state that is saved after the RAM
+    pub fn rtc_footer_size(&self) -> usize {
+        match self.mbc {
+            _ if !self.has_rtc => 0,
+            MBCType::HuC3 => HUC3_FOOTER_SIZE,
+            _ => RTC_FOOTER_SIZE,
+        }
+    }
+
+    /// Returns size of the save file, which has RTC state after the RAM
+    pub fn save_size(&self) -> usize {
+        self.ram_size() + self.rtc_footer_size()
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Memory {
+    /// All eight WRAM banks. Banks 2-7 are only used in CGB mode
     #[serde(with = "BigArray")]
-    pub wram: [u8; 0x2000],
+    pub wram: [u8; 0x8000],
+    /// WRAM bank mapped to $D000-$DFFF
+    pub wram_bank: u8,
+    /// If the system runs in CGB mode
+    pub cgb: bool,
     #[serde(with = "BigArray")]
     pub hram: [u8; 0x7F],
     pub info: CartridgeInfo,
     pub mbc: MBC,
+    /// Boot ROM is loaded manually like cartridge ROM, so it isn't saved in the save state
+    #[serde(skip)]
+    pub boot_rom: Vec<u8>,
+    /// If boot ROM is mapped over the start of cartridge ROM.
+    /// Unmapped for good by writing to $FF50
+    pub boot_rom_mapped: bool,
 }
 
 impl Memory {
-    pub fn new(rom_file: Vec<u8>) -> Self {
-        let info = CartridgeInfo::from_header(&rom_file[0x0100..=0x014F]);
-        println!("{:?}", info);
+    pub fn new(rom_file: Vec<u8>, boot_rom: Vec<u8>) -> Self {
+        let info = CartridgeInfo::from_rom(&rom_file);
+        if matches!(info.mbc, MBCType::MBC6 | MBCType::Unknown(_)) {
+            eprintln!("Cartridge type isn't supported, running it without an MBC");
+        }
         let mut mbc = MBC::init(info);
         mbc.load_rom(rom_file);
 
         Self {
-            wram: [0; 0x2000],
+            wram: [0; 0x8000],
+            wram_bank: 1,
+            cgb: false,
             hram: [0; 0x7F],
             mbc,
             info,
+            boot_rom,
+            boot_rom_mapped: true,
+        }
+    }
+
+    fn wram_index(&self, address: u16) -> usize {
+        match address {
+            0xC000..=0xCFFF => (address - 0xC000) as usize,
+            _ => self.wram_bank as usize * 0x1000 + (address - 0xD000) as usize,
         }
     }
 }
 
 impl MemoryAccess for Memory {
     fn get_range(&self) -> Vec<RangeInclusive<u16>> {
-        // ROM, external and work RAM, high RAM
-        vec![0x0000..=0x7FFF, 0xA000..=0xDFFF, 0xFF80..=0xFFFE]
+        // ROM, external and work RAM, boot ROM disable register, high RAM
+        let mut ranges = vec![
+            0x0000..=0x7FFF,
+            0xA000..=0xDFFF,
+            0xFF50..=0xFF50,
+            0xFF80..=0xFFFE,
+        ];
+        // WRAM bank
+        if self.cgb {
+            ranges.push(0xFF70..=0xFF70);
+        }
+        ranges
     }
     fn mem_read(&self, address: u16) -> u8 {
         match address {
+            0x0000..=0x00FF if self.boot_rom_mapped => self.boot_rom[address as usize],
             0x0000..=0x7FFF | 0xA000..=0xBFFF => self.mbc.read(address),
-            0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize],
+            0xC000..=0xDFFF => self.wram[self.wram_index(address)],
+            0xFF50 => 0xFF,
+            0xFF70 => 0xF8 | self.wram_bank,
             0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize],
             _ => {
                 eprintln!("Memory reading not implemented for {:#06X}", address);
@@ -116,7 +267,14 @@ impl MemoryAccess for Memory {
     fn mem_write(&mut self, address: u16, value: u8) {
         match address {
             0x0000..=0x7FFF | 0xA000..=0xBFFF => self.mbc.write(address, value),
-            0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize] = value,
+            0xC000..=0xDFFF => self.wram[self.wram_index(address)] = value,
+            0xFF50 => {
+                if value != 0 {
+                    self.boot_rom_mapped = false;
+                }
+            }
+            // Bank 0 can't be mapped to $D000, so it selects bank 1 instead
+            0xFF70 => self.wram_bank = (value & 0b111).max(1),
             0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize] = value,
             _ => eprintln!(
                 "Memory writing not implemented for {:#06X}. Tried to write {:#04X}",
@@ -143,18 +301,45 @@ pub struct MBC {
     info: CartridgeInfo,
     /// Used only by MBC1
     advanced_banking: bool,
+    /// Used only by MBC5 for the 9th bit of the ROM bank number
+    rom_bank_high: u8,
+    /// If rumble motor is currently on
+    pub rumble: bool,
+    /// If rumble motor has been on since it was last checked
+    pub rumbled: bool,
+    /// Used only by MBC3 cartridges with a timer
+    pub rtc: RTC,
+    /// Used only by MBC7
+    pub mbc7: MBC7,
+    /// Used only by MMM01
+    pub mmm01: MMM01,
+    /// Used only by HuC1 to map the infrared port in place of RAM
+    ir_mode: bool,
+    /// Used only by HuC3
+    pub huc3: HuC3,
+    /// Used only by Pocket Camera
+    pub camera: Camera,
 }
 
 impl MBC {
     pub fn init(info: CartridgeInfo) -> Self {
         Self {
             rom: vec![],
-            ram: vec![0; usize::from(0x2000 * info.ram_banks)],
+            ram: vec![0; info.ram_size()],
             save_ram: None,
             rom_bank: 1,
             ram_bank: 0,
             ram_enabled: false,
             advanced_banking: false,
+            rom_bank_high: 0,
+            rumble: false,
+            rumbled: false,
+            rtc: RTC::new(),
+            mbc7: MBC7::new(),
+            mmm01: MMM01::new(),
+            ir_mode: false,
+            huc3: HuC3::new(),
+            camera: Camera::new(),
             info,
         }
     }
@@ -165,32 +350,106 @@ impl MBC {
     }
 
     pub fn load_memory_map(&mut self, mut mmap: MmapMut, overwrite_mmap: bool) {
+        let ram_size = self.ram.len();
         if overwrite_mmap {
-            mmap.clone_from_slice(&self.ram);
+            mmap[..ram_size].clone_from_slice(&self.ram);
         } else {
-            self.ram = mmap.to_vec();
+            self.ram = mmap[..ram_size].to_vec();
+            match self.info.mbc {
+                _ if !self.info.has_rtc => {}
+                MBCType::HuC3 => self.huc3.load_footer(&mmap[ram_size..]),
+                _ => self.rtc.load_footer(&mmap[ram_size..]),
+            }
         }
         self.save_ram = Some(mmap);
+        self.save_rtc();
+    }
+
+    /// Writes RTC state into the memory mapped save file after RAM
+    fn save_rtc(&mut self) {
+        if !self.info.has_rtc {
+            return;
+        }
+        let ram_size = self.ram.len();
+        if let Some(mmap) = self.save_ram.as_deref_mut() {
+            match self.info.mbc {
+                MBCType::HuC3 => mmap[ram_size..ram_size + HUC3_FOOTER_SIZE]
+                    .copy_from_slice(&self.huc3.to_footer()),
+                _ => mmap[ram_size..ram_size + RTC_FOOTER_SIZE]
+                    .copy_from_slice(&self.rtc.to_footer()),
+            }
+        }
+    }
+
+    /// Cycles the cartridge forward by given amount of T-cycles.
+    /// Only cartridges with a real-time clock or a camera need this
+    pub fn run(&mut self, cycles: u64) {
+        if matches!(self.info.mbc, MBCType::PocketCamera) {
+            // Captured image is written straight to RAM, so it also has to be saved
+            if let Some(range) = self.camera.run(cycles, &mut self.ram) {
+                if let Some(mmap) = self.save_ram.as_deref_mut() {
+                    mmap[range.clone()].copy_from_slice(&self.ram[range]);
+                }
+            }
+            return;
+        }
+        // Clock state is saved every time it ticks, so it's up to date when emulator is closed
+        if !self.info.has_rtc {
+            return;
+        }
+        let ticked = match self.info.mbc {
+            MBCType::HuC3 => self.huc3.run(cycles),
+            _ => self.rtc.run(cycles),
+        };
+        if ticked {
+            self.save_rtc();
+        }
+    }
+
+    /// Returns the amount of T-cycles until the clock ticks or a capture finishes
+    pub fn next_event(&self) -> u64 {
+        match self.info.mbc {
+            MBCType::PocketCamera => self.camera.cycles_until_capture(),
+            _ if !self.info.has_rtc => u64::MAX,
+            MBCType::HuC3 => self.huc3.cycles_until_tick(),
+            _ => self.rtc.cycles_until_tick(),
+        }
     }
 
     /// Returns value from memory at address
     /// Should handle addresses between $0000-$7FFF and $A000-$BFFF
     pub fn read(&self, address: u16) -> u8 {
         match self.info.mbc {
-            MBCType::NoMBC => self.read_nombc(address),
-            MBCType::MBC1 => self.read_mbc1(address),
+            // Emulator refuses to load unsupported cartridges,
+            // but they're run without an MBC if the CPU is created directly
+            MBCType::NoMBC | MBCType::MBC6 | MBCType::Unknown(_) => self.read_nombc(address),
+            MBCType::MBC1 | MBCType::MBC1M => self.read_mbc1(address),
+            MBCType::MBC2 => self.read_mbc2(address),
+            MBCType::MMM01 => self.read_mmm01(address),
             MBCType::MBC3 => self.read_mbc3(address),
-            _ => todo!("MBC type not supported"),
+            MBCType::MBC5 => self.read_mbc5(address),
+            MBCType::MBC7 => self.read_mbc7(address),
+            MBCType::HuC1 => self.read_huc1(address),
+            MBCType::HuC3 => self.read_huc3(address),
+            MBCType::PocketCamera => self.read_camera(address),
         }
     }
     /// Writes value into memory or register
     /// Should handle addresses between $0000-$7FFF and $A000-$BFFF
     pub fn write(&mut self, address: u16, value: u8) {
         match self.info.mbc {
-            MBCType::NoMBC => self.write_nombc(address, value),
-            MBCType::MBC1 => self.write_mbc1(address, value),
+            MBCType::NoMBC | MBCType::MBC6 | MBCType::Unknown(_) => {
+                self.write_nombc(address, value)
+            }
+            MBCType::MBC1 | MBCType::MBC1M => self.write_mbc1(address, value),
+            MBCType::MBC2 => self.write_mbc2(address, value),
+            MBCType::MMM01 => self.write_mmm01(address, value),
             MBCType::MBC3 => self.write_mbc3(address, value),
-            _ => todo!("MBC type not supported"),
+            MBCType::MBC5 => self.write_mbc5(address, value),
+            MBCType::MBC7 => self.write_mbc7(address, value),
+            MBCType::HuC1 => self.write_huc1(address, value),
+            MBCType::HuC3 => self.write_huc3(address, value),
+            MBCType::PocketCamera => self.write_camera(address, value),
         }
     }
 
@@ -208,16 +467,20 @@ impl MBC {
     }
 
     fn read_nombc(&self, address: u16) -> u8 {
-        match address {
-            0x0000..=0x7FFF => self.rom[address as usize],
-            0xA000..=0xBFFF => self.ram[(address - 0xA000) as usize],
-            _ => 0xFF,
-        }
+        // Open bus past the end of a smaller ROM or RAM
+        let byte = match address {
+            0x0000..=0x7FFF => self.rom.get(address as usize),
+            0xA000..=0xBFFF => self.ram.get((address - 0xA000) as usize),
+            _ => None,
+        };
+        byte.copied().unwrap_or(0xFF)
     }
 
     fn write_nombc(&mut self, address: u16, value: u8) {
-        if !self.ram.is_empty() && address >= 0xA000 {
-            self.ram[(address - 0xA000) as usize] = value;
+        if address >= 0xA000 {
+            if let Some(byte) = self.ram.get_mut((address - 0xA000) as usize) {
+                *byte = value;
+            }
         }
     }
 
@@ -231,18 +494,26 @@ impl MBC {
                     address -= 0x4000;
                     address += (self.rom_bank as usize) * 0x4000;
                 }
+                // Multicarts select one of four 256 KiB games with the 2-bit register instead
+                let multicart = matches!(self.info.mbc, MBCType::MBC1M);
                 // If cartridge has >512 KiB ROM, the 2-bit register that is also used to select RAM banks
                 // can be used to select one of four large banks of 512 KiB memory
                 // It is also applied to the first address range if using advanced banking mode
-                if self.info.rom_banks > 32 && (address >= 0x4000 || self.advanced_banking) {
-                    // Mask out upper bit of high address if not enough banks
-                    let high_address = self.ram_bank
-                        & if self.info.rom_banks <= 64 {
-                            0b01
-                        } else {
-                            0b11
-                        };
-                    address += 0x20 * high_address as usize * 0x4000
+                if (self.info.rom_banks > 32 || multicart)
+                    && (address >= 0x4000 || self.advanced_banking)
+                {
+                    if multicart {
+                        address += 0x10 * self.ram_bank as usize * 0x4000;
+                    } else {
+                        // Mask out upper bit of high address if not enough banks
+                        let high_address = self.ram_bank
+                            & if self.info.rom_banks <= 64 {
+                                0b01
+                            } else {
+                                0b11
+                            };
+                        address += 0x20 * high_address as usize * 0x4000
+                    }
                 }
 
                 if self.rom.len() <= address {
@@ -289,7 +560,12 @@ impl MBC {
             0x2000..=0x3FFF => {
                 // Only needed amount of bits to change between all ROM banks
                 // are saved to the register, rest are masked out
-                let mut masked = self.mask_bank_number(value, self.info.rom_banks.clamp(0, 32));
+                // Bit 4 of the register isn't connected on multicarts
+                let mut masked = if matches!(self.info.mbc, MBCType::MBC1M) {
+                    value & 0b1111
+                } else {
+                    self.mask_bank_number(value, self.info.rom_banks.clamp(0, 32))
+                };
                 // If register is tried to set to 0, it should be incremented to 1
                 // The check is only done for the 5-bit version for the value though,
                 // so for example if only 3 bits are used,
@@ -334,6 +610,69 @@ impl MBC {
         };
     }
 
+    fn read_mbc2(&self, address: u16) -> u8 {
+        let mut address = address as usize;
+        match address {
+            0x0000..=0x3FFF => self.rom[address],
+            0x4000..=0x7FFF => {
+                address = (address - 0x4000) + self.rom_bank as usize * 0x4000;
+
+                if self.rom.len() <= address {
+                    eprintln!(
+                        "Tried to access ROM at {:#06X}, but length is only {:#06X}",
+                        address,
+                        self.rom.len()
+                    );
+                    return 0;
+                }
+                self.rom[address]
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return 0xFF;
+                }
+                // Only the lower 9 bits are used, so RAM is echoed across the whole range.
+                // RAM only stores the lower 4 bits of each byte, and the upper bits read as set
+                self.ram[address & 0x1FF] | 0xF0
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_mbc2(&mut self, address: u16, value: u8) {
+        match address {
+            // Bit 8 of the address selects which register is written to
+            0x0000..=0x3FFF => {
+                if address & 0x0100 == 0 {
+                    // Enable the RAM
+                    self.ram_enabled = (value & 0x0F) == 0x0A;
+                } else {
+                    // ROM bank register
+                    // Like on MBC1, 0 is incremented to 1 based on the whole 4-bit value,
+                    // so a bank number past the end of a smaller ROM can still map bank 0
+                    let mut bank = value & 0x0F;
+                    if bank == 0 {
+                        bank = 1;
+                    }
+                    self.rom_bank = self.mask_bank_number(bank, self.info.rom_banks);
+                }
+            }
+            // Write to RAM
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return;
+                }
+                let address = address as usize & 0x1FF;
+                let value = value & 0x0F;
+                self.ram[address] = value;
+                if let Some(mmap) = self.save_ram.as_deref_mut() {
+                    mmap[address] = value;
+                }
+            }
+            _ => {}
+        };
+    }
+
     fn read_mbc3(&self, address: u16) -> u8 {
         let mut address = address as usize;
         match address {
@@ -355,6 +694,10 @@ impl MBC {
                 if !self.ram_enabled {
                     return 0xFF;
                 }
+                // RTC registers are mapped in place of RAM when selected
+                if self.ram_bank >= 0x08 {
+                    return self.rtc.read(self.ram_bank);
+                }
                 address -= 0xA000;
                 address += self.ram_bank as usize * 0x2000;
 
@@ -385,12 +728,111 @@ impl MBC {
                 self.rom_bank = masked;
                 // println!("Selected ROM bank {}", self.rom_bank);
             }
-            // 2 bit bank register that is used to select both ROM and RAM banks
+            // Selects either a RAM bank or an RTC register
             0x4000..=0x5FFF => {
-                if self.info.ram_banks != 0 {
+                if self.info.has_rtc && (0x08..=0x0C).contains(&value) {
+                    self.ram_bank = value;
+                } else if self.info.ram_banks != 0 {
                     self.ram_bank = self.mask_bank_number(value, self.info.ram_banks);
                 }
             }
+            // Latch current time into the RTC registers
+            0x6000..=0x7FFF if self.info.has_rtc => self.rtc.write_latch(value),
+            // Write to RAM
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return;
+                }
+                if self.ram_bank >= 0x08 {
+                    self.rtc.write(self.ram_bank, value);
+                    self.save_rtc();
+                    return;
+                }
+                let mut address = address as usize;
+                address -= 0xA000;
+                address += self.ram_bank as usize * 0x2000;
+
+                if self.ram.len() <= address {
+                    eprintln!(
+                        "Tried to write {:#04X} into external RAM at {:#06X}, but RAM size is only {:#06X}",
+                        value,
+                        address,
+                        self.ram.len()
+                    );
+                    return;
+                }
+                self.ram[address] = value;
+                if let Some(mmap) = self.save_ram.as_deref_mut() {
+                    mmap[address] = value;
+                }
+            }
+            _ => {}
+        };
+    }
+
+    fn read_mbc5(&self, address: u16) -> u8 {
+        let mut address = address as usize;
+        match address {
+            0x0000..=0x3FFF => self.rom[address],
+            0x4000..=0x7FFF => {
+                // Unlike other MBCs, bank 0 can also be mapped here
+                let bank = ((self.rom_bank_high as usize) << 8 | self.rom_bank as usize)
+                    & (self.info.rom_banks as usize - 1);
+                address -= 0x4000;
+                address += bank * 0x4000;
+
+                if self.rom.len() <= address {
+                    eprintln!(
+                        "Tried to access ROM at {:#06X}, but length is only {:#06X}",
+                        address,
+                        self.rom.len()
+                    );
+                    return 0;
+                }
+                self.rom[address]
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return 0xFF;
+                }
+                address -= 0xA000;
+                address += self.ram_bank as usize * 0x2000;
+
+                if self.ram.len() <= address {
+                    eprintln!(
+                        "Tried to access external RAM at {:#06X}, but RAM size is only {:#06X}",
+                        address,
+                        self.ram.len()
+                    );
+                    return 0xFF;
+                }
+                self.ram[address]
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_mbc5(&mut self, address: u16, value: u8) {
+        match address {
+            // Enable the RAM. Unlike other MBCs, all bits are checked
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            // Lower 8 bits of ROM bank number
+            0x2000..=0x2FFF => self.rom_bank = value,
+            // 9th bit of ROM bank number
+            0x3000..=0x3FFF => self.rom_bank_high = value & 0b1,
+            // 4 bit RAM bank register
+            0x4000..=0x5FFF => {
+                let mut bank = value & 0b1111;
+                // On rumble cartridges bit 3 controls the motor instead
+                if self.info.has_rumble {
+                    self.rumble = bank & 0b1000 > 0;
+                    self.rumbled |= self.rumble;
+                    bank &= 0b0111;
+                }
+                if self.info.ram_banks != 0 {
+                    self.ram_bank = self.mask_bank_number(bank, self.info.ram_banks);
+                }
+            }
             // Write to RAM
             0xA000..=0xBFFF => {
                 if !self.ram_enabled {
@@ -417,4 +859,254 @@ impl MBC {
             _ => {}
         };
     }
+
+    fn read_mbc7(&self, address: u16) -> u8 {
+        let mut address = address as usize;
+        match address {
+            0x0000..=0x3FFF => self.rom[address],
+            0x4000..=0x7FFF => {
+                let bank = self.rom_bank as usize & (self.info.rom_banks as usize - 1);
+                address -= 0x4000;
+                address += bank * 0x4000;
+
+                if self.rom.len() <= address {
+                    eprintln!(
+                        "Tried to access ROM at {:#06X}, but length is only {:#06X}",
+                        address,
+                        self.rom.len()
+                    );
+                    return 0;
+                }
+                self.rom[address]
+            }
+            // Accelerometer and EEPROM registers
+            0xA000..=0xAFFF => {
+                if !self.ram_enabled || !self.mbc7.registers_enabled {
+                    return 0xFF;
+                }
+                self.mbc7.read(address as u16)
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_mbc7(&mut self, address: u16, value: u8) {
+        match address {
+            // Registers are enabled by writing to both of the enable registers
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x4000..=0x5FFF => self.mbc7.registers_enabled = value == 0x40,
+            // 7 bit ROM bank number
+            0x2000..=0x3FFF => self.rom_bank = value & 0x7F,
+            0xA000..=0xAFFF => {
+                if !self.ram_enabled || !self.mbc7.registers_enabled {
+                    return;
+                }
+                // EEPROM contents are kept in RAM so they're saved like battery RAM
+                let written = self.mbc7.write(address, value, &mut self.ram);
+                if let (Some(words), Some(mmap)) = (written, self.save_ram.as_deref_mut()) {
+                    let range = match words {
+                        WrittenWords::One(word) => word as usize * 2..word as usize * 2 + 2,
+                        WrittenWords::All => 0..self.ram.len(),
+                    };
+                    mmap[range.clone()].copy_from_slice(&self.ram[range]);
+                }
+            }
+            _ => {}
+        };
+    }
+
+    fn read_mmm01(&self, address: u16) -> u8 {
+        let address = address as usize;
+        match address {
+            0x0000..=0x7FFF => {
+                let bank =
+                    self.mmm01.rom_bank(address >= 0x4000) & (self.info.rom_banks as usize - 1);
+                let address = bank * 0x4000 + (address & 0x3FFF);
+
+                if self.rom.len() <= address {
+                    eprintln!(
+                        "Tried to access ROM at {:#06X}, but length is only {:#06X}",
+                        address,
+                        self.rom.len()
+                    );
+                    return 0;
+                }
+                self.rom[address]
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled || self.ram.is_empty() {
+                    return 0xFF;
+                }
+                let bank = self.mmm01.ram_bank() & (self.info.ram_banks as usize - 1);
+                self.ram[bank * 0x2000 + (address - 0xA000)]
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_mmm01(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x7FFF => {
+                if let Some(ram_enabled) = self.mmm01.write(address, value) {
+                    self.ram_enabled = ram_enabled;
+                }
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled || self.ram.is_empty() {
+                    return;
+                }
+                let bank = self.mmm01.ram_bank() & (self.info.ram_banks as usize - 1);
+                let address = bank * 0x2000 + (address as usize - 0xA000);
+                self.ram[address] = value;
+                if let Some(mmap) = self.save_ram.as_deref_mut() {
+                    mmap[address] = value;
+                }
+            }
+            _ => {}
+        };
+    }
+
+    /// Returns value from the switchable ROM bank. Used by the Hudson and camera mappers
+    fn read_rom_bank(&self, address: u16) -> u8 {
+        let bank = self.rom_bank as usize & (self.info.rom_banks as usize - 1);
+        let address = bank * 0x4000 + (address as usize - 0x4000);
+
+        if self.rom.len() <= address {
+            eprintln!(
+                "Tried to access ROM at {:#06X}, but length is only {:#06X}",
+                address,
+                self.rom.len()
+            );
+            return 0;
+        }
+        self.rom[address]
+    }
+
+    /// Returns index of the currently selected external RAM address,
+    /// or None if there's no RAM at the address
+    fn ram_index(&self, address: u16) -> Option<usize> {
+        let index = self.ram_bank as usize * 0x2000 + (address as usize - 0xA000);
+        if self.ram.len() <= index {
+            eprintln!(
+                "Tried to access external RAM at {:#06X}, but RAM size is only {:#06X}",
+                index,
+                self.ram.len()
+            );
+            return None;
+        }
+        Some(index)
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if let Some(index) = self.ram_index(address) {
+            self.ram[index] = value;
+            if let Some(mmap) = self.save_ram.as_deref_mut() {
+                mmap[index] = value;
+            }
+        }
+    }
+
+    fn read_huc1(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => self.rom[address as usize],
+            0x4000..=0x7FFF => self.read_rom_bank(address),
+            0xA000..=0xBFFF => {
+                // Infrared sensor never sees any light
+                if self.ir_mode {
+                    return 0xC0;
+                }
+                self.ram_index(address)
+                    .map(|index| self.ram[index])
+                    .unwrap_or(0xFF)
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_huc1(&mut self, address: u16, value: u8) {
+        match address {
+            // HuC1 doesn't have a RAM enable register, and maps either RAM or the infrared port
+            0x0000..=0x1FFF => self.ir_mode = value & 0x0F == 0x0E,
+            // 6 bit ROM bank number
+            0x2000..=0x3FFF => self.rom_bank = value & 0b11_1111,
+            // 2 bit RAM bank number
+            0x4000..=0x5FFF => self.ram_bank = value & 0b11,
+            // Writes to the infrared LED are ignored
+            0xA000..=0xBFFF if !self.ir_mode => self.write_ram(address, value),
+            _ => {}
+        };
+    }
+
+    fn read_huc3(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => self.rom[address as usize],
+            0x4000..=0x7FFF => self.read_rom_bank(address),
+            0xA000..=0xBFFF => match self.huc3.mode {
+                HuC3Mode::RAM | HuC3Mode::RAMReadOnly => self
+                    .ram_index(address)
+                    .map(|index| self.ram[index])
+                    .unwrap_or(0xFF),
+                _ => self.huc3.read(),
+            },
+            _ => 0xFF,
+        }
+    }
+
+    fn write_huc3(&mut self, address: u16, value: u8) {
+        match address {
+            // Selects what is mapped to $A000-$BFFF
+            0x0000..=0x1FFF => self.huc3.mode = HuC3Mode::from_value(value),
+            // 7 bit ROM bank number
+            0x2000..=0x3FFF => self.rom_bank = value & 0b111_1111,
+            // 2 bit RAM bank number
+            0x4000..=0x5FFF => self.ram_bank = value & 0b11,
+            0xA000..=0xBFFF => match self.huc3.mode {
+                HuC3Mode::RAM => self.write_ram(address, value),
+                _ => {
+                    if self.huc3.write(value) {
+                        self.save_rtc();
+                    }
+                }
+            },
+            _ => {}
+        };
+    }
+
+    fn read_camera(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => self.rom[address as usize],
+            0x4000..=0x7FFF => self.read_rom_bank(address),
+            0xA000..=0xBFFF => {
+                if self.camera.registers_mapped {
+                    return self.camera.read(address);
+                }
+                // RAM can be read even when it's disabled
+                self.ram_index(address)
+                    .map(|index| self.ram[index])
+                    .unwrap_or(0xFF)
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_camera(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            // 6 bit ROM bank number. Bank 0 can also be mapped to $4000
+            0x2000..=0x3FFF => self.rom_bank = value & 0b11_1111,
+            // 4 bit RAM bank number, or camera registers if bit 4 is set
+            0x4000..=0x5FFF => {
+                self.camera.registers_mapped = value & 0b1_0000 > 0;
+                self.ram_bank = value & 0b1111;
+            }
+            0xA000..=0xBFFF => {
+                if self.camera.registers_mapped {
+                    self.camera.write(address, value);
+                } else if self.ram_enabled {
+                    self.write_ram(address, value);
+                }
+            }
+            _ => {}
+        };
+    }
 }
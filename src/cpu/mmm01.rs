@@ -0,0 +1,128 @@
+use super::*;
+
+/// Registers of the MMM01 multicart mapper.
+/// Cartridge starts in a locked menu mode where the last 32 KiB of ROM is mapped,
+/// and the menu sets up the base bank and masks of the selected game before mapping it in.
+/// Once the game is mapped, the registers behave like MBC1 registers
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+pub struct MMM01 {
+    /// If selected game has been mapped in. Can't be undone without resetting
+    pub mapped: bool,
+    /// ROM bank bits 0-4
+    rom_bank_low: u8,
+    /// ROM bank bits 5-6, only writable before mapping
+    rom_bank_mid: u8,
+    /// ROM bank bits 7-8, only writable before mapping
+    rom_bank_high: u8,
+    /// Bits 1-4 of the ROM bank that the game can't change after mapping
+    rom_bank_mask: u8,
+    /// RAM bank bits 0-1
+    ram_bank_low: u8,
+    /// RAM bank bits 2-3, only writable before mapping
+    ram_bank_high: u8,
+    /// Bits 0-1 of the RAM bank that the game can't change after mapping
+    ram_bank_mask: u8,
+    /// Same as the banking mode register of MBC1
+    advanced_banking: bool,
+    /// Prevents the game from changing the banking mode
+    banking_mode_locked: bool,
+}
+
+impl MMM01 {
+    pub fn new() -> Self {
+        Self {
+            mapped: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            advanced_banking: false,
+            banking_mode_locked: false,
+        }
+    }
+
+    /// Returns ROM bank mapped to $0000-$3FFF or $4000-$7FFF,
+    /// before it's wrapped to the size of the ROM
+    pub fn rom_bank(&self, upper: bool) -> usize {
+        // Menu is stored in the last two banks
+        if !self.mapped {
+            return if upper { 0x1FF } else { 0x1FE };
+        }
+        let base = ((self.rom_bank_high as usize) << 7) | ((self.rom_bank_mid as usize) << 5);
+        if upper {
+            // Bank 0 of the game selects bank 1 like on MBC1.
+            // Only the bits the game controls are checked
+            let mut low = self.rom_bank_low;
+            if low & !self.rom_bank_mask & 0b1_1111 == 0 {
+                low |= 1;
+            }
+            base | low as usize
+        } else {
+            // Bank 0 of the game is the base bank with the bits the game controls cleared
+            base | (self.rom_bank_low & self.rom_bank_mask) as usize
+        }
+    }
+
+    /// Returns RAM bank mapped to $A000-$BFFF, before it's wrapped to the size of the RAM
+    pub fn ram_bank(&self) -> usize {
+        let low = if self.advanced_banking || !self.mapped {
+            self.ram_bank_low
+        } else {
+            self.ram_bank_low & self.ram_bank_mask
+        };
+        ((self.ram_bank_high << 2) | low) as usize
+    }
+
+    /// Writes to register at $0000-$7FFF.
+    /// Returns the new state of RAM enable if the register was written to
+    pub fn write(&mut self, address: u16, value: u8) -> Option<bool> {
+        match address {
+            0x0000..=0x1FFF => {
+                if !self.mapped {
+                    self.ram_bank_mask = (value >> 4) & 0b11;
+                    // Writing bit 6 maps the game in
+                    self.mapped = value & 0b0100_0000 > 0;
+                }
+                return Some(value & 0x0F == 0x0A);
+            }
+            0x2000..=0x3FFF => {
+                let low = value & 0b1_1111;
+                if self.mapped {
+                    self.rom_bank_low =
+                        (self.rom_bank_low & self.rom_bank_mask) | (low & !self.rom_bank_mask);
+                } else {
+                    self.rom_bank_low = low;
+                    self.rom_bank_mid = (value >> 5) & 0b11;
+                }
+            }
+            0x4000..=0x5FFF => {
+                let low = value & 0b11;
+                if self.mapped {
+                    self.ram_bank_low =
+                        (self.ram_bank_low & self.ram_bank_mask) | (low & !self.ram_bank_mask);
+                } else {
+                    self.ram_bank_low = low;
+                    self.ram_bank_high = (value >> 2) & 0b11;
+                    self.rom_bank_high = (value >> 4) & 0b11;
+                    self.banking_mode_locked = value & 0b0100_0000 > 0;
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !self.mapped {
+                    // Mask bits correspond to ROM bank bits 1-4
+                    self.rom_bank_mask = (value & 0b0011_1100) >> 1;
+                }
+                // The RAM/ROM multiplexing of bit 6 isn't used by any known game, so it's ignored
+                if !self.mapped || !self.banking_mode_locked {
+                    self.advanced_banking = value & 0b1 > 0;
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
@@ -1,5 +1,8 @@
 use super::*;
 
+pub mod fifo;
+use fifo::*;
+
 #[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
 pub struct PPUControl(u8);
 
@@ -33,6 +36,26 @@ bitflags! {
         const Y_FLIP   = 0b0100_0000;
         const X_FLIP   = 0b0010_0000;
         const PALETTE  = 0b0001_0000;
+        /// VRAM bank of the tile in CGB mode
+        const BANK     = 0b0000_1000;
+        /// Color palette in CGB mode
+        const CGB_PALETTE = 0b0000_0111;
+    }
+}
+
+/// Attributes of a background tile in CGB mode, which are stored in VRAM bank 1
+/// at the same position as the tile index
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct TileAttributes(u8);
+
+bitflags! {
+    impl TileAttributes: u8 {
+        /// Tile is drawn over objects if its color ID isn't 0
+        const PRIORITY = 0b1000_0000;
+        const Y_FLIP   = 0b0100_0000;
+        const X_FLIP   = 0b0010_0000;
+        const BANK     = 0b0000_1000;
+        const PALETTE  = 0b0000_0111;
     }
 }
 
@@ -81,7 +104,7 @@ impl OAM {
     }
 
     pub fn write(&mut self, address: u16, value: u8) {
-        let mut sprite = self.sprites[usize::from(address / 4)];
+        let sprite = &mut self.sprites[usize::from(address / 4)];
         match address % 4 {
             0 => sprite.y = value,
             1 => sprite.x = value,
@@ -92,6 +115,38 @@ impl OAM {
     }
 }
 
+/// M-cycles from writing to $FF46 until the first byte is copied
+pub const OAM_DMA_DELAY: u8 = 2;
+
+/// OAM DMA transfer, which copies one byte to OAM on every M-cycle
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+pub struct OAMDMA {
+    /// Value last written to $FF46
+    pub register: u8,
+    /// High byte of the source address of the running transfer
+    pub source: u8,
+    /// Index of the next byte to copy
+    pub index: u8,
+    /// If a transfer is running, which cuts the CPU off from most of memory
+    pub active: bool,
+    /// M-cycles left until a requested transfer starts.
+    /// A running transfer keeps going until then
+    pub delay: u8,
+}
+
+impl OAMDMA {
+    pub fn new() -> Self {
+        Self {
+            register: 0,
+            source: 0,
+            index: 0,
+            active: false,
+            delay: 0,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct DMGPalettes {
     bg: u8,
@@ -99,12 +154,71 @@ pub struct DMGPalettes {
     obj1: u8,
 }
 
-pub type DisplayMatrix = [[u8; 144]; 160];
+/// Color palette RAM of the CGB, which holds eight palettes of four RGB555 colors
+#[derive(Deserialize, Serialize)]
+pub struct CGBPalettes {
+    #[serde(with = "BigArray")]
+    pub data: [u8; 64],
+    /// Index of the byte accessed through the data register.
+    /// Bit 7 increments the index after every write
+    pub index: u8,
+}
+
+impl CGBPalettes {
+    pub fn new() -> Self {
+        Self {
+            // Palettes are initialized to white
+            data: [0xFF; 64],
+            index: 0,
+        }
+    }
+
+    /// Returns the RGB555 color of given color ID in given palette
+    pub fn get_color(&self, palette: u8, col_id: u8) -> u16 {
+        let index = (palette as usize * 4 + col_id as usize) * 2;
+        u16::from_le_bytes([self.data[index], self.data[index + 1]]) & 0x7FFF
+    }
+
+    fn read_data(&self) -> u8 {
+        self.data[(self.index & 0x3F) as usize]
+    }
+
+    fn write_data(&mut self, value: u8) {
+        self.data[(self.index & 0x3F) as usize] = value;
+        if self.index & 0b1000_0000 > 0 {
+            self.index = 0b1000_0000 | (self.index.wrapping_add(1) & 0x3F);
+        }
+    }
+}
+
+/// Which palette a pixel was drawn with in DMG mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layer {
+    /// Background or window
+    BG,
+    OBJ0,
+    OBJ1,
+}
+
+/// Colors of the display, indexed with `[x][y]`.
+/// In DMG mode these are 2-bit shades that the frontend maps to its palette,
+/// and in CGB mode or with a colorized DMG game they're 15-bit RGB555 colors
+pub type DisplayMatrix = [[u16; 144]; 160];
+
+/// Length of a frame in dots (T-cycles)
+const DOTS_PER_FRAME: u32 = 456 * 154;
+
+/// Layer each pixel of the display was drawn from, indexed with `[x][y]`
+pub type LayerMatrix = [[Layer; 144]; 160];
 
 fn empty_display() -> DisplayMatrix {
     [[0; 144]; 160]
 }
 
+fn empty_layers() -> LayerMatrix {
+    [[Layer::BG; 144]; 160]
+}
+
 /// The graphics processing unit
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Deserialize, Serialize)]
@@ -112,33 +226,65 @@ pub struct PPU {
     #[serde(skip)]
     #[serde(default = "empty_display")]
     pub display: DisplayMatrix,
+    /// Lets the frontend color each layer with its own palette
+    #[serde(skip)]
+    #[serde(default = "empty_layers")]
+    pub layers: LayerMatrix,
+    /// If the PPU renders in CGB mode
+    pub cgb: bool,
+    /// If a DMG game is colored with the CGB palette RAM, like the CGB does for DMG games
+    pub compat: bool,
+    /// Both VRAM banks. Bank 1 is only used in CGB mode
     #[serde(with = "BigArray")]
-    pub vram: [u8; 0x2000],
+    pub vram: [u8; 0x4000],
+    /// VRAM bank mapped to $8000-$9FFF
+    pub vram_bank: u8,
     pub oam: OAM,
-    pub oam_dma_source: u8,
-    pub oam_dma_timer: u16,
+    pub oam_dma: OAMDMA,
     pub control: PPUControl,
     pub lx: u16,
     pub ly: u8,
     pub bg_x: u8,
     pub bg_y: u8,
+    /// Window X position plus 7, like it's written to WX
     pub win_x: u8,
     pub win_y: u8,
+    /// Set once LY has matched WY with the window enabled, after which the window can be drawn
+    /// until the end of the frame
+    pub win_y_triggered: bool,
+    /// Internal line counter of the window, which only advances on lines where the window is drawn
+    pub win_line: u8,
     pub palettes: DMGPalettes,
+    pub bg_palettes: CGBPalettes,
+    pub obj_palettes: CGBPalettes,
     pub interrupt_request: InterruptFlag,
     pub stat_enable: STATEnable,
     pub mode: u8,
     pub lyc: u8,
+    /// Set on the T-cycle that HBlank starts, so HDMA can copy a block
+    pub hblank_started: bool,
+    pub fetcher: PixelFetcher,
+    /// State of the STAT interrupt line, which only requests an interrupt on a rising edge
+    pub stat_line: bool,
+    /// Set when the LCD is turned on, since the first frame after that isn't shown
+    pub blank_frame: bool,
+    /// Dots since the last frame ended while the LCD is off
+    pub off_dots: u32,
+    /// Set when a frame ends, either at VBlank or after a frame's worth of dots with the LCD off
+    pub frame_ended: bool,
 }
 
 impl PPU {
     pub fn new() -> Self {
         Self {
             display: empty_display(),
-            vram: [0; 0x2000],
+            layers: empty_layers(),
+            cgb: false,
+            compat: false,
+            vram: [0; 0x4000],
+            vram_bank: 0,
             oam: OAM::new(),
-            oam_dma_source: 0,
-            oam_dma_timer: 0,
+            oam_dma: OAMDMA::new(),
             control: PPUControl::from_bits_truncate(0),
             lx: 0,
             ly: 0,
@@ -146,98 +292,252 @@ impl PPU {
             bg_y: 0,
             win_x: 0,
             win_y: 0,
+            win_y_triggered: false,
+            win_line: 0,
             palettes: DMGPalettes {
                 bg: 0,
                 obj0: 0,
                 obj1: 0,
             },
+            bg_palettes: CGBPalettes::new(),
+            obj_palettes: CGBPalettes::new(),
             interrupt_request: InterruptFlag::from_bits_truncate(0),
             stat_enable: STATEnable::from_bits_truncate(0),
-            mode: 2,
+            // LCD is off until the boot ROM turns it on
+            mode: 0,
             lyc: 0,
+            hblank_started: false,
+            fetcher: PixelFetcher::new(),
+            stat_line: false,
+            blank_frame: false,
+            off_dots: 0,
+            frame_ended: false,
         }
     }
 
-    pub fn cycle(&mut self) {
+    /// Cycles the PPU forward by up to given amount of dots, skipping over dots where
+    /// nothing but the position on the line changes. Stops early once HBlank or VBlank starts,
+    /// so the CPU can react to them. Returns the amount of dots that were run
+    pub fn run(&mut self, dots: u64) -> u64 {
         self.interrupt_request = InterruptFlag::from_bits_truncate(0);
-        // Decrement simulated OAM DMA timer
-        // When it is non-zero, CPU memory access should be limited
-        if self.oam_dma_timer > 0 {
-            self.oam_dma_timer -= 1;
+        self.hblank_started = false;
+        let mut ran = 0;
+        while ran < dots {
+            // First dot is always run normally, so the STAT line is up to date
+            // after registers have been written to
+            if ran > 0 {
+                let idle = self.idle_dots().min(dots - ran);
+                if self.control.intersects(PPUControl::ENABLE) {
+                    self.lx += idle as u16;
+                } else {
+                    self.off_dots += idle as u32;
+                }
+                ran += idle;
+                if ran == dots {
+                    break;
+                }
+            }
+            self.cycle();
+            ran += 1;
+            if self.hblank_started || self.interrupt_request.intersects(InterruptFlag::VBLANK) {
+                break;
+            }
+        }
+        ran
+    }
+
+    /// Returns the amount of upcoming dots where nothing but the position on the line changes
+    fn idle_dots(&self) -> u64 {
+        if !self.control.intersects(PPUControl::ENABLE) {
+            return (DOTS_PER_FRAME - 1 - self.off_dots) as u64;
+        }
+        match self.mode {
+            3 => 0,
+            _ if self.ly < 144 && self.lx < 80 => 79 - self.lx as u64,
+            _ => 455 - self.lx as u64,
+        }
+    }
+
+    /// Returns the amount of dots until the PPU might do something the CPU has to know about,
+    /// like request an interrupt or start HBlank
+    pub fn next_event(&self) -> u64 {
+        if !self.control.intersects(PPUControl::ENABLE) {
+            return (DOTS_PER_FRAME - self.off_dots) as u64;
+        }
+        match self.mode {
+            // Every pixel that's left takes at least a dot
+            3 => 160 - self.fetcher.lcd_x as u64,
+            _ if self.ly < 144 && self.lx < 80 => 80 - self.lx as u64 + 160,
+            _ => 456 - self.lx as u64,
+        }
+    }
+
+    /// Cycles the PPU forward by one dot
+    fn cycle(&mut self) {
+        // PPU is frozen while the LCD is off,
+        // but frames still have to end so the emulator keeps running
+        if !self.control.intersects(PPUControl::ENABLE) {
+            self.off_dots += 1;
+            if self.off_dots == DOTS_PER_FRAME {
+                self.off_dots = 0;
+                self.frame_ended = true;
+            }
+            return;
         }
 
         if self.lx < 455 {
             self.lx += 1;
-            if self.mode != 1 {
-                if self.lx == 80 {
-                    self.update_mode(3);
-                } else if self.lx == 80 + 289 {
-                    self.update_mode(0);
-                }
+            if self.lx == 80 && self.ly < 144 {
+                self.mode = 3;
+                self.start_pixel_transfer();
+            } else if self.mode == 3 && self.pixel_transfer_dot() {
+                // Length of mode 3 depends on scrolling, the window and objects
+                self.mode = 0;
+                self.hblank_started = true;
             }
         } else {
             self.lx = 0;
-
+            self.ly = if self.ly == 153 { 0 } else { self.ly + 1 };
             match self.ly {
-                0..=143 => {
-                    self.update_mode(2);
-                    self.draw_scanline(self.ly)
-                }
+                0..=143 => self.mode = 2,
                 144 => {
                     self.interrupt_request.insert(InterruptFlag::VBLANK);
-                    self.update_mode(1);
-                }
-                153 => {
-                    self.ly = 0;
-                    self.update_mode(2);
-                    return;
+                    self.mode = 1;
+                    self.frame_ended = true;
+                    self.blank_frame = false;
+                    // Window starts over on the next frame
+                    self.win_y_triggered = false;
+                    self.win_line = 0;
                 }
                 _ => {}
             }
-            self.ly += 1;
-
-            // Check for LYC=LY interrupt if its enabled
-            if self.stat_enable.intersects(STATEnable::LYC) && self.lyc == self.ly {
-                self.interrupt_request.insert(InterruptFlag::LCD);
-            }
         }
+        self.update_stat_line();
     }
 
-    fn update_mode(&mut self, mode: u8) {
-        self.mode = mode;
-        // Send interrupt if enabled
-        if (mode == 2 && self.stat_enable.intersects(STATEnable::Mode2))
-            || (mode == 1 && self.stat_enable.intersects(STATEnable::Mode1))
-            || (mode == 0 && self.stat_enable.intersects(STATEnable::Mode0))
-        {
+    /// STAT interrupt sources are ORed together into a single line,
+    /// and the interrupt is only requested when the line goes from low to high.
+    /// This means that a source can't fire while another one is keeping the line high
+    fn update_stat_line(&mut self) {
+        let line = (self.stat_enable.intersects(STATEnable::LYC) && self.lyc == self.ly)
+            || (self.stat_enable.intersects(STATEnable::Mode2) && self.mode == 2)
+            || (self.stat_enable.intersects(STATEnable::Mode1) && self.mode == 1)
+            || (self.stat_enable.intersects(STATEnable::Mode0) && self.mode == 0);
+        if line && !self.stat_line {
             self.interrupt_request.insert(InterruptFlag::LCD);
         }
+        self.stat_line = line;
+    }
+
+    /// Writes to LCD control, turning the LCD on or off if bit 7 changes
+    fn set_control(&mut self, value: u8) {
+        let was_enabled = self.control.intersects(PPUControl::ENABLE);
+        self.control = PPUControl::from_bits_truncate(value);
+        match (was_enabled, self.control.intersects(PPUControl::ENABLE)) {
+            (true, false) => {
+                // Turning the LCD off resets LY and leaves the screen blank
+                self.lx = 0;
+                self.ly = 0;
+                self.mode = 0;
+                self.stat_line = false;
+                self.off_dots = 0;
+                self.clear_display();
+            }
+            (false, true) => {
+                // First line after turning the LCD on has no OAM scan,
+                // and the first frame isn't shown
+                self.lx = 0;
+                self.ly = 0;
+                self.mode = 0;
+                self.blank_frame = true;
+                self.win_y_triggered = false;
+                self.win_line = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Fills the display with the color of a blank screen
+    fn clear_display(&mut self) {
+        let blank = if self.cgb || self.compat { 0x7FFF } else { 0 };
+        self.display = [[blank; 144]; 160];
+        self.layers = empty_layers();
     }
 
     /// Get color value from given palette
-    fn get_palette_color(&mut self, col_id: u8, palette: u8) -> u8 {
-        (palette >> (2 * col_id)) & 0b11
+    fn get_palette_color(&self, col_id: u8, palette: u8) -> u16 {
+        ((palette >> (2 * col_id)) & 0b11) as u16
     }
 
-    /// Saves given color into the display buffer
-    fn set_pixel(&mut self, x: u8, y: u8, col: u8) {
+    /// Saves given color and the layer it was drawn from into the display buffer
+    fn set_pixel(&mut self, x: u8, y: u8, col: u16, layer: Layer) {
         self.display[x as usize][y as usize] = col;
+        self.layers[x as usize][y as usize] = layer;
     }
 
-    /// Returns the tile index from specified tile map at specified coordinates
-    fn get_tile_index(&self, x: u8, y: u8, tile_map: bool) -> u8 {
+    /// Writes to VRAM in the currently mapped bank
+    pub fn write_vram(&mut self, address: u16, value: u8) {
+        self.vram[self.vram_index(address)] = value;
+    }
+
+    fn vram_index(&self, address: u16) -> usize {
+        self.vram_bank as usize * 0x2000 + (address - 0x8000) as usize
+    }
+
+    /// Returns the position of a tile in the specified tile map at specified coordinates
+    fn get_tile_map_index(&self, x: u8, y: u8, tile_map: bool) -> usize {
         // Get tile index in tile map
         let tile_map_index = u16::from(y / 8)
             .wrapping_mul(32)
             .wrapping_add((x / 8) as u16);
         // Get start position in memory of selected tile map
         let tile_map_root: u16 = if tile_map { 0x1C00 } else { 0x1800 };
-        // Get index of tile data from tile map
-        self.vram[usize::from(tile_map_root + tile_map_index)]
+        usize::from(tile_map_root + tile_map_index)
+    }
+
+    /// Returns the tile index from specified tile map at specified coordinates
+    fn get_tile_index(&self, x: u8, y: u8, tile_map: bool) -> u8 {
+        self.vram[self.get_tile_map_index(x, y, tile_map)]
+    }
+
+    /// Returns the data of the first 256 tiles on the background, 20 tiles per row.
+    /// Super Game Boy transfers read the screen like this
+    pub fn screen_tile_data(&self) -> Vec<u8> {
+        let tile_map = self.control.intersects(PPUControl::BG_TILE_MAP);
+        let addressing_mode = !self.control.intersects(PPUControl::TILE_DATA_AREA);
+        let mut data = Vec::with_capacity(0x1000);
+        for i in 0..256u16 {
+            let (x, y) = ((i % 20) as u8 * 8, (i / 20) as u8 * 8);
+            let tile = self.get_tile_index(x, y, tile_map) as u16;
+            let mut address = 16 * tile;
+            if addressing_mode && tile < 128 {
+                address += 0x1000;
+            }
+            data.extend_from_slice(&self.vram[address as usize..address as usize + 16]);
+        }
+        data
+    }
+
+    /// Returns the attributes of the tile at specified coordinates.
+    /// Tiles don't have attributes in DMG mode
+    fn get_tile_attributes(&self, x: u8, y: u8, tile_map: bool) -> TileAttributes {
+        if !self.cgb {
+            return TileAttributes::empty();
+        }
+        TileAttributes::from_bits_truncate(
+            self.vram[0x2000 + self.get_tile_map_index(x, y, tile_map)],
+        )
     }
 
     /// Returns the color ID of given tile at specified coordinates
-    fn get_tile_color(&self, x: u8, y: u8, tile_index: u8, addressing_mode: bool) -> u8 {
+    fn get_tile_color(
+        &self,
+        x: u8,
+        y: u8,
+        tile_index: u8,
+        addressing_mode: bool,
+        bank: bool,
+    ) -> u8 {
         // Get memory position of tile inside VRAM (one tile is 16 bytes)
         // and add target row to it to get address of the two bytes
         // that make up a tile row of color data
@@ -248,6 +548,10 @@ impl PPU {
         if addressing_mode && tile_index < 128 {
             byte_index += 0x1000;
         }
+        // Tile data can also be read from the second VRAM bank in CGB mode
+        if bank {
+            byte_index += 0x2000;
+        }
 
         // Get the color bytes from VRAM
         let a_byte = self.vram[usize::from(byte_index)];
@@ -260,151 +564,151 @@ impl PPU {
         (a as u8) | ((b as u8) << 1)
     }
 
-    /// Returns list of sprites that occupy given scanline
-    fn get_sprites(&self, y: u8) -> Vec<OAMSprite> {
+    /// Returns list of sprites that occupy given scanline, with their position in OAM
+    fn get_sprites(&self, y: u8) -> Vec<(u8, OAMSprite)> {
         // Convert screen Y to object space,
         // where y = 0 completely hides the object
         let obj_y = y + 16;
-        // Get object height based on current LCD control
-        let height = if self.control.intersects(PPUControl::OBJ_SIZE) {
-            16
-        } else {
-            8
-        };
+        let height = self.sprite_height();
 
-        let mut sprites: Vec<OAMSprite> = vec![];
-        for sprite in self.oam.sprites {
+        let mut sprites: Vec<(u8, OAMSprite)> = vec![];
+        for (index, sprite) in self.oam.sprites.into_iter().enumerate() {
             if obj_y < sprite.y.saturating_add(height) && obj_y >= sprite.y {
-                sprites.push(sprite);
+                sprites.push((index as u8, sprite));
             }
             if sprites.len() == 10 {
                 break;
             }
         }
-        // Sort sprites by their x coordinate
-        sprites.sort_by_key(|sprite| sprite.x);
+        // Sort sprites by their x coordinate. Sorting is stable,
+        // so sprites with the same x coordinate stay in OAM order like on the DMG.
+        // In CGB mode priority only depends on the order in OAM
+        if !self.cgb {
+            sprites.sort_by_key(|(_, sprite)| sprite.x);
+        }
         sprites
     }
 
-    fn draw_scanline(&mut self, y: u8) {
-        let sprites = self.get_sprites(y);
-        for x in 0..=159u8 {
-            let mut drawn_sprite: Option<&OAMSprite> = None;
-            let mut sprite_col = 0u8;
-            if self.control.intersects(PPUControl::OBJ_ENABLE) {
-                // Convert screen X to object space
-                let obj_x = x + 8;
-                for sprite in &sprites {
-                    if obj_x < sprite.x.saturating_add(8) && obj_x >= sprite.x {
-                        let mut tile_x = (x as i16) - ((sprite.x as i16) - 8);
-                        tile_x %= 8;
-                        if sprite.flags.intersects(SpriteFlags::X_FLIP) {
-                            tile_x = 7 - tile_x;
-                        }
-
-                        let mut tile_y = (y as i16) - ((sprite.y as i16) - 16);
-                        tile_y %= 8;
-                        if sprite.flags.intersects(SpriteFlags::Y_FLIP) {
-                            tile_y = 7 - tile_y;
-                        }
-
-                        let col_id = self.get_tile_color(
-                            tile_x as u8,
-                            tile_y as u8,
-                            sprite.tile_index,
-                            false,
-                        );
-                        // With objects, color ID of 0 means transparent,
-                        // so render background or window instead
-                        if col_id != 0 {
-                            drawn_sprite = Some(sprite);
-                            let palette = if sprite.flags.intersects(SpriteFlags::PALETTE) {
-                                self.palettes.obj1
-                            } else {
-                                self.palettes.obj0
-                            };
-                            sprite_col = self.get_palette_color(col_id, palette);
-                            break;
-                        }
-                    }
-                }
-            }
+    /// Returns object height based on current LCD control
+    fn sprite_height(&self) -> u8 {
+        if self.control.intersects(PPUControl::OBJ_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
 
-            let mut use_bg_sprite = false;
-            if let Some(sprite) = drawn_sprite {
-                // If object priority flag is true,
-                // background / window can be rendered on top of it
-                if !sprite.flags.intersects(SpriteFlags::PRIORITY) {
-                    self.set_pixel(x, y, sprite_col);
-                    continue;
-                }
-                use_bg_sprite = true;
+    /// Returns the DMG shade of a pixel from the FIFOs,
+    /// and the layer whose palette it was drawn with
+    fn get_dmg_pixel(&self, bg: BGPixel, obj: Option<ObjPixel>) -> (u16, Layer) {
+        let mut sprite_col = None;
+        if let Some(obj) = obj.filter(|obj| obj.color != 0) {
+            let (palette, layer) = if obj.flags.intersects(SpriteFlags::PALETTE) {
+                (self.palettes.obj1, Layer::OBJ1)
+            } else {
+                (self.palettes.obj0, Layer::OBJ0)
+            };
+            let col = self.get_palette_color(obj.color, palette);
+            // If object priority flag is true,
+            // background / window can be rendered on top of it
+            if !obj.flags.intersects(SpriteFlags::PRIORITY) {
+                return (col, layer);
             }
+            sprite_col = Some((col, layer));
+        }
 
-            // If background and window are disabled, render object or just blank
-            if !self.control.intersects(PPUControl::BG_WINDOW_ENABLE) {
-                if use_bg_sprite {
-                    self.set_pixel(x, y, sprite_col);
-                } else {
-                    let col = self.get_palette_color(0, self.palettes.bg);
-                    self.set_pixel(x, y, col);
-                }
-                continue;
-            }
+        // If background and window are disabled, render object or just blank
+        if !self.control.intersects(PPUControl::BG_WINDOW_ENABLE) {
+            return sprite_col.unwrap_or((self.get_palette_color(0, self.palettes.bg), Layer::BG));
+        }
 
-            // Get window pixel instead of background if
-            // window is enabled and pixel is inside window bounds
-            let col_id = if self.control.intersects(PPUControl::WINDOW_ENABLE)
-                && x >= self.win_x
-                && y >= self.win_y
-            {
-                let tile = self.get_tile_index(
-                    x - self.win_x,
-                    y - self.win_y,
-                    self.control.intersects(PPUControl::WINDOW_TILE_MAP),
-                );
-                self.get_tile_color(
-                    x - self.win_x,
-                    y - self.win_y,
-                    tile,
-                    !self.control.intersects(PPUControl::TILE_DATA_AREA),
-                )
-            } else {
-                let tile = self.get_tile_index(
-                    x.wrapping_add(self.bg_x),
-                    y.wrapping_add(self.bg_y),
-                    self.control.intersects(PPUControl::BG_TILE_MAP),
-                );
-                // Coordinates of background tiles may wrap around
-                self.get_tile_color(
-                    x.wrapping_add(self.bg_x),
-                    y.wrapping_add(self.bg_y),
-                    tile,
-                    !self.control.intersects(PPUControl::TILE_DATA_AREA),
-                )
-            };
+        match sprite_col {
             // If pixel color ID is 0, render sprite instead
-            if col_id == 0 && use_bg_sprite {
-                self.set_pixel(x, y, sprite_col);
-            }
+            Some(sprite_col) if bg.color == 0 => sprite_col,
             // Otherwise render background / window pixel
-            else {
-                let col = self.get_palette_color(col_id, self.palettes.bg);
-                self.set_pixel(x, y, col);
+            _ => (
+                self.get_palette_color(bg.color, self.palettes.bg),
+                Layer::BG,
+            ),
+        }
+    }
+
+    /// Loads the palettes a DMG game is colored with into the palette RAM.
+    /// Background uses BG palette 0 and objects use OBJ palettes 0 and 1
+    pub fn load_compat_palettes(&mut self, palettes: &CompatPalettes) {
+        for i in 0..4 {
+            let bg = palettes.bg[i].to_le_bytes();
+            let obj0 = palettes.obj0[i].to_le_bytes();
+            let obj1 = palettes.obj1[i].to_le_bytes();
+            self.bg_palettes.data[i * 2..i * 2 + 2].copy_from_slice(&bg);
+            self.obj_palettes.data[i * 2..i * 2 + 2].copy_from_slice(&obj0);
+            self.obj_palettes.data[8 + i * 2..8 + i * 2 + 2].copy_from_slice(&obj1);
+        }
+        self.compat = true;
+    }
+
+    /// Returns the RGB555 color of a pixel from the FIFOs
+    fn get_cgb_pixel(&self, bg: BGPixel, obj: Option<ObjPixel>) -> u16 {
+        // Background and window are always drawn in CGB mode
+        if let Some(obj) = obj.filter(|obj| obj.color != 0) {
+            // Clearing LCDC bit 0 makes objects always appear on top,
+            // otherwise either priority bit lets non-zero background colors cover them
+            let bg_on_top = self.control.intersects(PPUControl::BG_WINDOW_ENABLE)
+                && bg.color != 0
+                && (obj.flags.intersects(SpriteFlags::PRIORITY)
+                    || bg.attributes.intersects(TileAttributes::PRIORITY));
+            if !bg_on_top {
+                let palette = (obj.flags & SpriteFlags::CGB_PALETTE).bits();
+                return self.obj_palettes.get_color(palette, obj.color);
             }
         }
+        let palette = (bg.attributes & TileAttributes::PALETTE).bits();
+        self.bg_palettes.get_color(palette, bg.color)
+    }
+
+    /// Mixes a background and an object pixel from the FIFOs and draws it on the display.
+    /// Palettes are read when the pixel is drawn, so changing them mid-line has an effect
+    fn draw_pixel(&mut self, x: u8, y: u8, bg: BGPixel, obj: Option<ObjPixel>) {
+        // Display stays blank for the first frame after turning the LCD on
+        if self.blank_frame {
+            return;
+        }
+        let obj = obj.filter(|_| self.control.intersects(PPUControl::OBJ_ENABLE));
+        if self.cgb {
+            let col = self.get_cgb_pixel(bg, obj);
+            self.set_pixel(x, y, col, Layer::BG);
+            return;
+        }
+        let (shade, layer) = self.get_dmg_pixel(bg, obj);
+        // Colorized games use the shade as a color ID of the CGB palettes
+        let col = if self.compat {
+            match layer {
+                Layer::BG => self.bg_palettes.get_color(0, shade as u8),
+                Layer::OBJ0 => self.obj_palettes.get_color(0, shade as u8),
+                Layer::OBJ1 => self.obj_palettes.get_color(1, shade as u8),
+            }
+        } else {
+            shade
+        };
+        self.set_pixel(x, y, col, layer);
     }
 }
 
 impl MemoryAccess for PPU {
     fn get_range(&self) -> Vec<RangeInclusive<u16>> {
         // VRAM, OAM, LCD I/O
-        vec![0x8000..=0x9FFF, 0xFE00..=0xFE9F, 0xFF40..=0xFF4B]
+        let mut ranges = vec![0x8000..=0x9FFF, 0xFE00..=0xFE9F, 0xFF40..=0xFF4B];
+        // VRAM bank and color palettes
+        if self.cgb {
+            ranges.push(0xFF4F..=0xFF4F);
+            ranges.push(0xFF68..=0xFF6B);
+        }
+        ranges
     }
 
     fn mem_read(&self, address: u16) -> u8 {
         match address {
-            0x8000..=0x9FFF => self.vram[(address - 0x8000) as usize],
+            0x8000..=0x9FFF => self.vram[self.vram_index(address)],
             0xFE00..=0xFE9F => self.oam.read(address - 0xFE00),
             0xFF40 => self.control.bits(),
             0xFF41 => {
@@ -415,34 +719,44 @@ impl MemoryAccess for PPU {
             0xFF43 => self.bg_x,
             0xFF44 => self.ly,
             0xFF45 => self.lyc,
-            0xFF46 => self.oam_dma_source,
+            0xFF46 => self.oam_dma.register,
             0xFF47 => self.palettes.bg,
             0xFF48 => self.palettes.obj0,
             0xFF49 => self.palettes.obj1,
             0xFF4A => self.win_y,
-            0xFF4B => self.win_x + 7,
+            0xFF4B => self.win_x,
+            0xFF4F => 0xFE | self.vram_bank,
+            0xFF68 => self.bg_palettes.index | 0x40,
+            0xFF69 => self.bg_palettes.read_data(),
+            0xFF6A => self.obj_palettes.index | 0x40,
+            0xFF6B => self.obj_palettes.read_data(),
             _ => 0xFF,
         }
     }
     fn mem_write(&mut self, address: u16, value: u8) {
         match address {
-            0x8000..=0x9FFF => self.vram[(address - 0x8000) as usize] = value,
+            0x8000..=0x9FFF => self.write_vram(address, value),
             0xFE00..=0xFE9F => self.oam.write(address - 0xFE00, value),
-            0xFF40 => self.control = PPUControl::from_bits_truncate(value),
+            0xFF40 => self.set_control(value),
             0xFF41 => self.stat_enable = STATEnable::from_bits_truncate(value),
             0xFF42 => self.bg_y = value,
             0xFF43 => self.bg_x = value,
             0xFF45 => self.lyc = value,
             0xFF46 => {
-                self.oam_dma_source = value;
-                // Setting the timer activates OAM DMA transfer on CPU
-                self.oam_dma_timer = 640;
+                // Transfer is (re)started after a short delay by the CPU
+                self.oam_dma.register = value;
+                self.oam_dma.delay = OAM_DMA_DELAY;
             }
             0xFF47 => self.palettes.bg = value,
             0xFF48 => self.palettes.obj0 = value,
             0xFF49 => self.palettes.obj1 = value,
             0xFF4A => self.win_y = value,
-            0xFF4B => self.win_x = value.saturating_sub(7),
+            0xFF4B => self.win_x = value,
+            0xFF4F => self.vram_bank = value & 0b1,
+            0xFF68 => self.bg_palettes.index = value & 0xBF,
+            0xFF69 => self.bg_palettes.write_data(value),
+            0xFF6A => self.obj_palettes.index = value & 0xBF,
+            0xFF6B => self.obj_palettes.write_data(value),
             _ => {}
         }
     }
@@ -0,0 +1,294 @@
+use super::*;
+use std::collections::VecDeque;
+
+/// Dots the fetcher spends on one tile before it can push its pixels
+const FETCH_DOTS: u8 = 6;
+/// Dots at the start of mode 3 before the first tile is fetched,
+/// which makes mode 3 last 172 dots without scrolling, window or objects
+const STARTUP_DOTS: u8 = 7;
+/// Minimum amount of dots that fetching an object pauses the pixel transfer for
+const OBJ_FETCH_DOTS: u8 = 6;
+
+/// Background or window pixel waiting in the FIFO
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct BGPixel {
+    pub color: u8,
+    pub attributes: TileAttributes,
+}
+
+/// Object pixel waiting in the FIFO
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct ObjPixel {
+    pub color: u8,
+    pub flags: SpriteFlags,
+    /// Position of the object in OAM, which decides priority in CGB mode
+    pub oam_index: u8,
+}
+
+/// State of the pixel transfer (mode 3), which pushes pixels to the LCD one dot at a time
+#[derive(Deserialize, Serialize)]
+pub struct PixelFetcher {
+    bg_fifo: VecDeque<BGPixel>,
+    obj_fifo: VecDeque<ObjPixel>,
+    /// Dots spent fetching the current tile
+    dots: u8,
+    /// Dots left before the first tile is fetched
+    startup: u8,
+    /// Column of the next tile, counted from the left edge of the background or window
+    tile_x: u8,
+    /// If window tiles are fetched instead of background
+    window: bool,
+    /// Row of the window that is drawn on this line
+    window_y: u8,
+    /// Set when WX=166 matches on the last pixel of a line,
+    /// which makes the window cover the whole next line
+    window_next_line: bool,
+    /// Pixels still thrown away from the start of the line because of fine horizontal scroll
+    discard: u8,
+    /// X coordinate of the next pixel pushed to the LCD
+    pub lcd_x: u8,
+    /// Objects on the current line that haven't been fetched yet, with their OAM index
+    sprites: Vec<(u8, OAMSprite)>,
+    /// Object that is being fetched and the amount of dots left
+    obj_fetch: Option<((u8, OAMSprite), u8)>,
+}
+
+impl PixelFetcher {
+    pub fn new() -> Self {
+        Self {
+            bg_fifo: VecDeque::with_capacity(16),
+            obj_fifo: VecDeque::with_capacity(8),
+            dots: 0,
+            startup: 0,
+            tile_x: 0,
+            window: false,
+            window_y: 0,
+            window_next_line: false,
+            discard: 0,
+            lcd_x: 0,
+            sprites: vec![],
+            obj_fetch: None,
+        }
+    }
+}
+
+impl PPU {
+    /// Resets the fetcher at the start of mode 3
+    pub(super) fn start_pixel_transfer(&mut self) {
+        let sprites = self.get_sprites(self.ly);
+        if self.ly == self.win_y && self.control.intersects(PPUControl::WINDOW_ENABLE) {
+            self.win_y_triggered = true;
+        }
+        let fetcher = &mut self.fetcher;
+        fetcher.bg_fifo.clear();
+        fetcher.obj_fifo.clear();
+        fetcher.dots = 0;
+        fetcher.startup = STARTUP_DOTS;
+        fetcher.tile_x = 0;
+        fetcher.window = false;
+        fetcher.discard = self.bg_x & 0b111;
+        fetcher.lcd_x = 0;
+        fetcher.sprites = sprites;
+        fetcher.obj_fetch = None;
+        if std::mem::take(&mut fetcher.window_next_line)
+            && self.win_y_triggered
+            && self.control.intersects(PPUControl::WINDOW_ENABLE)
+        {
+            self.start_window(0);
+        }
+    }
+
+    /// Switches the fetcher from background to window,
+    /// throwing away given amount of pixels from the left edge of the window
+    fn start_window(&mut self, discard: u8) {
+        let fetcher = &mut self.fetcher;
+        fetcher.window = true;
+        fetcher.window_y = self.win_line;
+        fetcher.bg_fifo.clear();
+        fetcher.dots = 0;
+        fetcher.tile_x = 0;
+        fetcher.discard = discard;
+        self.win_line = self.win_line.wrapping_add(1);
+    }
+
+    /// Runs the pixel transfer for one dot. Returns true once the whole line has been drawn
+    pub(super) fn pixel_transfer_dot(&mut self) -> bool {
+        if self.fetcher.startup > 0 {
+            self.fetcher.startup -= 1;
+            return false;
+        }
+
+        // Pixel transfer is paused while an object is fetched
+        if let Some((sprite, dots)) = self.fetcher.obj_fetch {
+            if dots > 1 {
+                self.fetcher.obj_fetch = Some((sprite, dots - 1));
+            } else {
+                self.fetcher.obj_fetch = None;
+                self.push_sprite(sprite);
+            }
+            return false;
+        }
+
+        // Window starts once its left edge is reached, throwing away the fetched background pixels.
+        // WX under 7 moves the window past the left edge of the screen
+        if self.control.intersects(PPUControl::WINDOW_ENABLE)
+            && self.win_y_triggered
+            && self.fetcher.lcd_x + 7 >= self.win_x
+        {
+            if !self.fetcher.window {
+                self.start_window(7u8.saturating_sub(self.win_x));
+            }
+            // WX=166 matches on the last pixel, even if the window already covers the line,
+            // and every match makes the window cover the whole next line
+            if self.fetcher.lcd_x == 159 && self.win_x == 166 {
+                self.fetcher.window_next_line = true;
+            }
+        }
+
+        // Fetch the next tile, and push it once the FIFO has been emptied.
+        // This comes first, so an object starting where a tile starts waits for the tile
+        if self.fetcher.dots < FETCH_DOTS {
+            self.fetcher.dots += 1;
+        }
+        if self.fetcher.dots == FETCH_DOTS && self.fetcher.bg_fifo.is_empty() {
+            let row = self.fetch_bg_row();
+            self.fetcher.bg_fifo.extend(row);
+            self.fetcher.tile_x = self.fetcher.tile_x.wrapping_add(1);
+            self.fetcher.dots = 0;
+        }
+
+        // Objects are fetched once their left edge is reached
+        if self.control.intersects(PPUControl::OBJ_ENABLE)
+            && self.fetcher.discard == 0
+            && !self.fetcher.bg_fifo.is_empty()
+        {
+            let lcd_x = self.fetcher.lcd_x;
+            if let Some(index) = self
+                .fetcher
+                .sprites
+                .iter()
+                .position(|(_, sprite)| sprite.x <= lcd_x + 8)
+            {
+                let sprite = self.fetcher.sprites.remove(index);
+                // Fetch takes longer if the background fetcher has to finish its tile first
+                let penalty =
+                    OBJ_FETCH_DOTS + 5u8.saturating_sub(sprite.1.x.wrapping_add(self.bg_x) & 0b111);
+                // This dot is the first one of the fetch
+                self.fetcher.obj_fetch = Some((sprite, penalty - 1));
+                return false;
+            }
+        }
+
+        // Push a pixel to the LCD
+        let Some(bg) = self.fetcher.bg_fifo.pop_front() else {
+            return false;
+        };
+        if self.fetcher.discard > 0 {
+            self.fetcher.discard -= 1;
+            return false;
+        }
+        let obj = self.fetcher.obj_fifo.pop_front();
+        self.draw_pixel(self.fetcher.lcd_x, self.ly, bg, obj);
+        self.fetcher.lcd_x += 1;
+        self.fetcher.lcd_x == 160
+    }
+
+    /// Returns the next row of 8 background or window pixels.
+    /// Registers are read at the time of the fetch, so changing them mid-line has an effect
+    fn fetch_bg_row(&self) -> [BGPixel; 8] {
+        let (x, y, tile_map) = if self.fetcher.window {
+            (
+                self.fetcher.tile_x.wrapping_mul(8),
+                self.fetcher.window_y,
+                self.control.intersects(PPUControl::WINDOW_TILE_MAP),
+            )
+        } else {
+            // Coordinates of background tiles may wrap around
+            (
+                ((self.bg_x / 8).wrapping_add(self.fetcher.tile_x) & 31) * 8,
+                self.ly.wrapping_add(self.bg_y),
+                self.control.intersects(PPUControl::BG_TILE_MAP),
+            )
+        };
+        let tile = self.get_tile_index(x, y, tile_map);
+        let attributes = self.get_tile_attributes(x, y, tile_map);
+        // Flipping only changes the position inside the tile
+        let pixel_y = if attributes.intersects(TileAttributes::Y_FLIP) {
+            7 - y % 8
+        } else {
+            y
+        };
+        std::array::from_fn(|i| {
+            let pixel_x = if attributes.intersects(TileAttributes::X_FLIP) {
+                7 - i as u8
+            } else {
+                i as u8
+            };
+            BGPixel {
+                color: self.get_tile_color(
+                    pixel_x,
+                    pixel_y,
+                    tile,
+                    !self.control.intersects(PPUControl::TILE_DATA_AREA),
+                    attributes.intersects(TileAttributes::BANK),
+                ),
+                attributes,
+            }
+        })
+    }
+
+    /// Fetches a row of an object and mixes it into the object FIFO
+    fn push_sprite(&mut self, (oam_index, sprite): (u8, OAMSprite)) {
+        let height = self.sprite_height();
+        let mut tile_y = (self.ly + 16 - sprite.y) % height;
+        // Flipping a 8x16 object swaps its tiles too
+        if sprite.flags.intersects(SpriteFlags::Y_FLIP) {
+            tile_y = height - 1 - tile_y;
+        }
+        // 8x16 objects use an even tile for the top half and the next one for the bottom
+        let tile_index = if height == 16 {
+            (sprite.tile_index & 0xFE) | (tile_y / 8)
+        } else {
+            sprite.tile_index
+        };
+        for i in 0..8u8 {
+            // Pixels left of the screen edge are never drawn
+            let Some(offset) = (sprite.x + i).checked_sub(self.fetcher.lcd_x + 8) else {
+                continue;
+            };
+            let tile_x = if sprite.flags.intersects(SpriteFlags::X_FLIP) {
+                7 - i
+            } else {
+                i
+            };
+            let pixel = ObjPixel {
+                color: self.get_tile_color(
+                    tile_x,
+                    tile_y % 8,
+                    tile_index,
+                    false,
+                    self.cgb && sprite.flags.intersects(SpriteFlags::BANK),
+                ),
+                flags: sprite.flags,
+                oam_index,
+            };
+            let fifo = &mut self.fetcher.obj_fifo;
+            let offset = offset as usize;
+            while fifo.len() <= offset {
+                fifo.push_back(ObjPixel {
+                    color: 0,
+                    flags: SpriteFlags::empty(),
+                    oam_index: 0xFF,
+                });
+            }
+            // Object already in the FIFO stays on top unless it's transparent.
+            // In CGB mode the object earlier in OAM wins instead
+            let existing = fifo[offset];
+            if existing.color == 0
+                || (self.cgb && pixel.color != 0 && oam_index < existing.oam_index)
+            {
+                fifo[offset] = pixel;
+            }
+        }
+    }
+}
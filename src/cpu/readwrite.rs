@@ -11,15 +11,52 @@ pub trait MemoryAccess {
 }
 
 impl CPU {
-    /// Reads from given memory address
-    pub fn read(&self, address: u16) -> u8 {
+    /// Returns whether the CPU is cut off from given address at the moment.
+    /// VRAM can't be accessed while the PPU draws (mode 3) and OAM while it's scanned or drawn
+    /// (modes 2 and 3). During OAM DMA only HRAM and the I/O registers can be accessed
+    fn is_blocked(&self, address: u16) -> bool {
+        if !self.access_restrictions {
+            return false;
+        }
+        if self.ppu.oam_dma.active && address < 0xFF00 {
+            return true;
+        }
+        // PPU stays in mode 0 while the LCD is off, so everything can be accessed then
+        match address {
+            0x8000..=0x9FFF => self.ppu.mode == 3,
+            0xFE00..=0xFE9F => matches!(self.ppu.mode, 2 | 3),
+            _ => false,
+        }
+    }
+
+    /// Reads from given memory address, catching up the component that's mapped there first.
+    /// Reading from memory that's cut off returns $FF
+    pub fn read(&mut self, address: u16) -> u8 {
+        // ROM doesn't change over time, and it's read all the time
+        if address >= 0x8000 {
+            if let Some(component) = Self::component_at(address) {
+                self.sync(component);
+            }
+        }
+        if self.is_blocked(address) {
+            return 0xFF;
+        }
+        self.read_unrestricted(address)
+    }
+
+    /// Reads from given memory address, ignoring the access restrictions.
+    /// Used by DMA transfers and the debugger
+    pub fn read_unrestricted(&self, address: u16) -> u8 {
         let targets: Vec<&dyn MemoryAccess> = vec![
             &self.mem,
             &self.ppu,
             &self.apu,
             &self.input,
             &self.timer,
+            &self.serial,
             &self.istate,
+            &self.speed,
+            &self.hdma,
         ];
         for target in targets {
             for range in target.get_range() {
@@ -33,19 +70,60 @@ impl CPU {
     }
 
     /// Reads 16-bit value from given memory address
-    pub fn read_16(&self, address: u16) -> u16 {
+    pub fn read_16(&mut self, address: u16) -> u16 {
         u16::from_le_bytes([self.read(address), self.read(address + 1)])
     }
 
-    /// Writes to given memory address
+    /// Writes to given memory address, catching up the component that's mapped there first.
+    /// Writes to memory that's cut off are ignored
     pub fn write(&mut self, address: u16, value: u8) {
+        let component = Self::component_at(address);
+        if let Some(component) = component {
+            self.sync(component);
+        }
+        // Resetting the divider can clock the serial port and DIV-APU,
+        // so they have to be caught up with the old value
+        if address == 0xFF04 {
+            self.sync(Component::Serial);
+            self.sync(Component::APU);
+        }
+        if self.is_blocked(address) {
+            return;
+        }
+        self.write_unrestricted(address, value);
+
+        // Writing might have changed when the next event happens
+        match component {
+            // STAT interrupt might be requested on the next dot
+            Some(Component::PPU) if address >= 0xFF00 => {
+                self.scheduler
+                    .schedule(Component::PPU, self.system_cycle_length());
+            }
+            Some(component) => self.sync(component),
+            None => {}
+        }
+        if address == 0xFF04 {
+            self.sync(Component::Serial);
+            self.sync(Component::APU);
+        }
+    }
+
+    /// Writes to given memory address, ignoring the access restrictions
+    pub fn write_unrestricted(&mut self, address: u16, value: u8) {
+        // CGB games take over from the boot ROM in CGB mode
+        if address == 0xFF50 && value != 0 && self.mem.boot_rom_mapped && self.mem.info.cgb {
+            self.enter_cgb_mode();
+        }
         let targets: Vec<&mut dyn MemoryAccess> = vec![
             &mut self.mem,
             &mut self.ppu,
             &mut self.apu,
             &mut self.input,
             &mut self.timer,
+            &mut self.serial,
             &mut self.istate,
+            &mut self.speed,
+            &mut self.hdma,
         ];
         for target in targets {
             for range in target.get_range() {
@@ -92,16 +170,33 @@ impl CPU {
         self.write(self.reg.sp, bytes[0]);
     }
 
-    /// Starts OAM DMA transfer, which copies memory from given source address to OAM
-    pub fn oam_dma(&mut self, address: u8) {
-        let source_address = (address as u16) * 0x100;
-        for sprite_index in 0..40 {
-            let sprite_address = source_address + (sprite_index * 4);
-            let mut data = [0u8; 4];
-            for i in 0..4u16 {
-                data[i as usize] = self.read(sprite_address + i);
+    /// Runs OAM DMA for one M-cycle, copying the next byte to OAM.
+    /// Writing to $FF46 restarts the transfer after a delay, during which the old one keeps going
+    pub fn oam_dma_cycle(&mut self) {
+        if self.ppu.oam_dma.active {
+            // PPU has to see the old OAM up to this point
+            self.sync(Component::PPU);
+            let dma = &mut self.ppu.oam_dma;
+            // Echo RAM and above is mirrored to WRAM
+            let mut address = u16::from_be_bytes([dma.source, dma.index]);
+            if address >= 0xE000 {
+                address -= 0x2000;
+            }
+            let index = dma.index;
+            dma.index += 1;
+            dma.active = dma.index < 160;
+            let value = self.read_unrestricted(address);
+            self.ppu.oam.write(index as u16, value);
+        }
+
+        let dma = &mut self.ppu.oam_dma;
+        if dma.delay > 0 {
+            dma.delay -= 1;
+            if dma.delay == 0 {
+                dma.source = dma.register;
+                dma.index = 0;
+                dma.active = true;
             }
-            self.ppu.oam.sprites[sprite_index as usize] = OAMSprite::from(data);
         }
     }
 }
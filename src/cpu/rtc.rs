@@ -0,0 +1,247 @@
+use super::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size of the RTC state that is appended to the end of save files.
+/// Uses the same format as BGB and VBA-M, so saves can be moved between emulators
+pub const RTC_FOOTER_SIZE: usize = 48;
+/// RTC oscillator runs at 32768 Hz, which is divided down to once per second
+const CYCLES_PER_SECOND: u32 = 4194304;
+
+/// Clock counter registers of the MBC3 real-time clock
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct RTCRegisters {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    /// 9-bit day counter
+    pub days: u16,
+    /// Clock is stopped while halted
+    pub halt: bool,
+    /// Set when day counter overflows, and stays set until cleared
+    pub day_carry: bool,
+}
+
+impl RTCRegisters {
+    fn new() -> Self {
+        Self {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halt: false,
+            day_carry: false,
+        }
+    }
+
+    /// Returns value of register selected with given RAM bank number ($08-$0C)
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            0x08 => self.seconds,
+            0x09 => self.minutes,
+            0x0A => self.hours,
+            0x0B => (self.days & 0xFF) as u8,
+            0x0C => {
+                ((self.day_carry as u8) << 7) | ((self.halt as u8) << 6) | (self.days >> 8) as u8
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            // Counters only have as many bits as needed for their regular range
+            0x08 => self.seconds = value & 0b11_1111,
+            0x09 => self.minutes = value & 0b11_1111,
+            0x0A => self.hours = value & 0b1_1111,
+            0x0B => self.days = (self.days & 0x100) | value as u16,
+            0x0C => {
+                self.days = (self.days & 0xFF) | ((value as u16 & 0b1) << 8);
+                self.halt = value & 0b0100_0000 > 0;
+                self.day_carry = value & 0b1000_0000 > 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances the clock by one second
+    fn tick(&mut self) {
+        // Counters that are set out of their range keep counting
+        // until their bits overflow, without carrying over to the next counter
+        self.seconds = (self.seconds + 1) & 0b11_1111;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0b11_1111;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0b1_1111;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+        self.days += 1;
+        if self.days == 512 {
+            self.days = 0;
+            self.day_carry = true;
+        }
+    }
+
+    fn in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Advances the clock by given amount of seconds
+    fn advance(&mut self, mut seconds: u64) {
+        // Tick manually until all counters are back in their regular range
+        while seconds > 0 && !self.in_range() {
+            self.tick();
+            seconds -= 1;
+        }
+        let total = self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 3600
+            + self.days as u64 * 86400
+            + seconds;
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        let days = total / 86400;
+        if days >= 512 {
+            self.day_carry = true;
+        }
+        self.days = (days % 512) as u16;
+    }
+
+    fn footer_values(self) -> [u32; 5] {
+        [
+            self.seconds as u32,
+            self.minutes as u32,
+            self.hours as u32,
+            self.read(0x0B) as u32,
+            self.read(0x0C) as u32,
+        ]
+    }
+
+    fn from_footer_values(values: &[u32]) -> Self {
+        let mut registers = Self::new();
+        for (register, value) in (0x08..=0x0C).zip(values) {
+            registers.write(register, *value as u8);
+        }
+        registers
+    }
+}
+
+/// Real-time clock of MBC3 cartridges
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+pub struct RTC {
+    pub registers: RTCRegisters,
+    /// Copy of the registers that is visible to the Game Boy.
+    /// Updated by writing $00 and then $01 to $6000-$7FFF
+    pub latched: RTCRegisters,
+    /// T-cycles since the clock last ticked
+    cycles: u32,
+    /// Previous value written to the latch register
+    latch_value: u8,
+}
+
+impl RTC {
+    pub fn new() -> Self {
+        Self {
+            registers: RTCRegisters::new(),
+            latched: RTCRegisters::new(),
+            cycles: 0,
+            latch_value: 0xFF,
+        }
+    }
+
+    /// Cycles the clock forward by given amount of T-cycles.
+    /// Returns true if the clock ticked
+    pub fn run(&mut self, cycles: u64) -> bool {
+        if self.registers.halt {
+            return false;
+        }
+        let total = self.cycles as u64 + cycles;
+        self.cycles = (total % CYCLES_PER_SECOND as u64) as u32;
+        let ticks = total / CYCLES_PER_SECOND as u64;
+        for _ in 0..ticks {
+            self.registers.tick();
+        }
+        ticks > 0
+    }
+
+    /// Returns the amount of T-cycles until the clock ticks
+    pub fn cycles_until_tick(&self) -> u64 {
+        if self.registers.halt {
+            return u64::MAX;
+        }
+        (CYCLES_PER_SECOND - self.cycles) as u64
+    }
+
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        // Writing seconds resets the divider that counts towards the next second
+        if register == 0x08 {
+            self.cycles = 0;
+        }
+        self.registers.write(register, value);
+        // Written value is visible without latching again
+        self.latched.write(register, value);
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_value == 0x00 && value == 0x01 {
+            self.latched = self.registers;
+        }
+        self.latch_value = value;
+    }
+
+    /// Returns clock state as a save file footer, with current time as the timestamp
+    pub fn to_footer(&self) -> [u8; RTC_FOOTER_SIZE] {
+        let mut footer = [0; RTC_FOOTER_SIZE];
+        let values = self
+            .registers
+            .footer_values()
+            .into_iter()
+            .chain(self.latched.footer_values());
+        for (chunk, value) in footer.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        footer[40..].copy_from_slice(&unix_time().to_le_bytes());
+        footer
+    }
+
+    /// Loads clock state from a save file footer,
+    /// and advances the clock by the time that has passed since it was saved
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        if footer.len() < RTC_FOOTER_SIZE {
+            return;
+        }
+        let values: Vec<u32> = footer[..40]
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        self.registers = RTCRegisters::from_footer_values(&values[..5]);
+        self.latched = RTCRegisters::from_footer_values(&values[5..]);
+
+        let timestamp = u64::from_le_bytes(footer[40..48].try_into().unwrap());
+        // Timestamp is 0 if the footer was just added to an older save file
+        if timestamp != 0 && !self.registers.halt {
+            self.registers
+                .advance(unix_time().saturating_sub(timestamp));
+        }
+    }
+}
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,209 @@
+use super::*;
+
+/// Parts of the system that aren't cycled along with the CPU,
+/// but are caught up to the current time only when needed
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Component {
+    PPU,
+    Timer,
+    Serial,
+    APU,
+    Cartridge,
+}
+
+const COMPONENTS: [Component; 5] = [
+    Component::PPU,
+    Component::Timer,
+    Component::Serial,
+    Component::APU,
+    Component::Cartridge,
+];
+
+/// Keeps track of time and of the next moment each component has something to do,
+/// like requesting an interrupt. Components are caught up to the current time
+/// when their event comes up or when the CPU accesses their registers
+#[derive(Deserialize, Serialize)]
+pub struct Scheduler {
+    /// T-cycles the CPU has run for. Runs twice as fast as the rest of the system in double speed mode
+    pub now: u64,
+    /// Time each component was last caught up to
+    synced: [u64; 5],
+    /// Time of the next event of each component, or `u64::MAX` if there is none
+    events: [u64; 5],
+    /// Time of the earliest event
+    next: u64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            synced: [0; 5],
+            events: [0; 5],
+            next: 0,
+        }
+    }
+
+    /// Returns the amount of T-cycles since given component was caught up,
+    /// and marks it as caught up to the current time
+    pub fn elapsed(&mut self, component: Component) -> u64 {
+        let elapsed = self.now - self.synced[component as usize];
+        self.synced[component as usize] = self.now;
+        elapsed
+    }
+
+    /// Returns the time given component was last caught up to
+    pub fn synced(&self, component: Component) -> u64 {
+        self.synced[component as usize]
+    }
+
+    /// Schedules the next event of given component after given amount of T-cycles
+    pub fn schedule(&mut self, component: Component, cycles: u64) {
+        self.events[component as usize] = self.now.saturating_add(cycles);
+        self.next = self.events.iter().copied().min().unwrap();
+    }
+
+    /// Returns the time of the earliest event
+    pub fn next_event(&self) -> u64 {
+        self.next
+    }
+
+    /// Returns a component whose event has come up
+    pub fn due(&self) -> Option<Component> {
+        if self.now < self.next {
+            return None;
+        }
+        COMPONENTS
+            .into_iter()
+            .find(|component| self.events[*component as usize] <= self.now)
+    }
+}
+
+impl CPU {
+    /// Returns how many T-cycles of the CPU one cycle of the rest of the system takes
+    pub fn system_cycle_length(&self) -> u64 {
+        if self.speed.double_speed {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Returns the component whose registers or memory are mapped to given address
+    pub fn component_at(address: u16) -> Option<Component> {
+        match address {
+            0x0000..=0x7FFF | 0xA000..=0xBFFF => Some(Component::Cartridge),
+            0x8000..=0x9FFF | 0xFE00..=0xFE9F | 0xFF40..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6B => {
+                Some(Component::PPU)
+            }
+            0xFF01..=0xFF02 => Some(Component::Serial),
+            0xFF04..=0xFF07 => Some(Component::Timer),
+            0xFF10..=0xFF3F => Some(Component::APU),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the 16-bit divider at given time
+    fn div_at(&self, time: u64) -> u16 {
+        let synced = self.scheduler.synced(Component::Timer);
+        if time >= synced {
+            self.timer.div.wrapping_add((time - synced) as u16)
+        } else {
+            self.timer.div.wrapping_sub((synced - time) as u16)
+        }
+    }
+
+    /// Catches up components whose events have come up
+    pub fn run_events(&mut self) {
+        while let Some(component) = self.scheduler.due() {
+            self.sync(component);
+        }
+    }
+
+    /// Catches up every component to the current time
+    pub fn sync_all(&mut self) {
+        for component in COMPONENTS {
+            self.sync(component);
+        }
+    }
+
+    /// Catches up given component to the current time and schedules its next event
+    pub fn sync(&mut self, component: Component) {
+        match component {
+            Component::PPU => {
+                #[cfg(feature = "puffin")]
+                puffin::profile_scope_if!(self.profiling, "PPU");
+                self.sync_ppu();
+            }
+            Component::Timer => {
+                #[cfg(feature = "puffin")]
+                puffin::profile_scope_if!(self.profiling, "Timer");
+                let cycles = self.scheduler.elapsed(Component::Timer);
+                if self.timer.run(cycles) {
+                    self.request_interrupt(InterruptFlag::TIMER);
+                }
+                self.scheduler
+                    .schedule(Component::Timer, self.timer.next_event());
+            }
+            Component::Serial => {
+                #[cfg(feature = "puffin")]
+                puffin::profile_scope_if!(self.profiling, "Serial");
+                let div = self.div_at(self.scheduler.synced(Component::Serial));
+                let cycles = self.scheduler.elapsed(Component::Serial);
+                if self.serial.run(div, cycles) {
+                    self.request_interrupt(InterruptFlag::SERIAL);
+                }
+                let div = self.div_at(self.scheduler.now);
+                self.scheduler
+                    .schedule(Component::Serial, self.serial.next_event(div));
+            }
+            Component::APU => {
+                #[cfg(feature = "puffin")]
+                puffin::profile_scope_if!(self.profiling, "APU");
+                let length = self.system_cycle_length();
+                let start = self.scheduler.synced(Component::APU);
+                let cycles = self.scheduler.elapsed(Component::APU) / length;
+                // Divider runs twice as fast in double speed mode, so a higher bit is used.
+                // Rest of the system cycles on the first and third T-cycle of each M-cycle
+                let (div, bit) = if self.speed.double_speed {
+                    (self.div_at(start).wrapping_sub(1), 13)
+                } else {
+                    (self.div_at(start), 12)
+                };
+                self.apu.run(cycles, div, length as u16, bit);
+                let div = self
+                    .div_at(self.scheduler.now)
+                    .wrapping_sub(length as u16 - 1);
+                let next = self.apu.next_event(div, length as u16, bit);
+                self.scheduler
+                    .schedule(Component::APU, next.saturating_mul(length));
+            }
+            Component::Cartridge => {
+                let length = self.system_cycle_length();
+                let cycles = self.scheduler.elapsed(Component::Cartridge) / length;
+                self.mem.mbc.run(cycles);
+                let next = self.mem.mbc.next_event();
+                self.scheduler
+                    .schedule(Component::Cartridge, next.saturating_mul(length));
+            }
+        }
+    }
+
+    /// Catches up the PPU, stopping at HBlank and VBlank to run the things that happen then
+    fn sync_ppu(&mut self) {
+        let length = self.system_cycle_length();
+        let mut dots = self.scheduler.elapsed(Component::PPU) / length;
+        while dots > 0 {
+            dots -= self.ppu.run(dots);
+            self.request_interrupt(self.ppu.interrupt_request);
+            if self.sgb.enabled && self.ppu.interrupt_request.intersects(InterruptFlag::VBLANK) {
+                self.sgb.vblank(&self.ppu);
+            }
+            if self.ppu.hblank_started && self.hdma.hblank_active {
+                self.hdma_hblank();
+            }
+        }
+        self.scheduler
+            .schedule(Component::PPU, self.ppu.next_event() * length);
+    }
+}
@@ -0,0 +1,234 @@
+use super::*;
+use std::io::Write;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct SerialControl(u8);
+
+bitflags! {
+    impl SerialControl: u8 {
+        const TRANSFER_ENABLE = 0b1000_0000;
+        const INTERNAL_CLOCK  = 0b0000_0001;
+    }
+}
+
+/// Something connected to the other end of the link port,
+/// like another Game Boy, a printer or just a logger
+pub trait SerialDevice: Send {
+    /// Called when the Game Boy starts a transfer using its internal clock.
+    /// Receives the byte that is shifted out of SB, and returns the byte that is shifted in.
+    /// May block until the other end has answered
+    fn transfer(&mut self, byte: u8) -> u8;
+
+    /// Polled while the Game Boy waits for a transfer using an external clock.
+    /// Receives the current value of SB, and returns the byte that is shifted in
+    /// if the device clocked a transfer
+    fn poll_external(&mut self, _byte: u8) -> Option<u8> {
+        None
+    }
+
+    /// Called when the Game Boy stops waiting for an external clock without a transfer happening,
+    /// like when the game switches to the internal clock
+    fn cancel_external(&mut self) {}
+
+    /// Called after every frame's worth of cycles,
+    /// so the device can keep the Game Boy in step with the other end
+    fn sync(&mut self) {}
+}
+
+/// Amount of T-cycles between calls to `SerialDevice::sync`
+const SYNC_INTERVAL: u32 = 70224;
+
+/// Serial device that prints sent bytes to stdout as text
+pub struct StdoutLogger;
+
+impl SerialDevice for StdoutLogger {
+    fn transfer(&mut self, byte: u8) -> u8 {
+        print!("{}", byte as char);
+        let _ = std::io::stdout().flush();
+        // Nothing drives the data line, so it's read as high
+        0xFF
+    }
+}
+
+/// Serial port controller
+#[derive(Deserialize, Serialize)]
+pub struct Serial {
+    /// Serial transfer data (SB)
+    pub data: u8,
+    /// Serial transfer control (SC)
+    pub control: SerialControl,
+    /// Byte that is being shifted into SB during a transfer
+    pub incoming: u8,
+    /// Amount of bits left to shift in current transfer
+    pub bits_left: u8,
+    /// Value of DIV bit used as the internal serial clock during previous cycle
+    pub last_div_bit: bool,
+    /// T-cycles since connected device was last synced
+    pub sync_counter: u32,
+    pub request_interrupt: bool,
+    #[serde(skip)]
+    pub device: Option<Box<dyn SerialDevice>>,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Self {
+            data: 0,
+            control: SerialControl::from_bits_truncate(0),
+            incoming: 0xFF,
+            bits_left: 0,
+            last_div_bit: false,
+            sync_counter: 0,
+            request_interrupt: false,
+            device: None,
+        }
+    }
+
+    /// Cycles the serial port forward by given amount of T-cycles, starting from given divider value.
+    /// Skips over the cycles where the serial clock doesn't tick.
+    /// Returns true if an interrupt was requested
+    pub fn run(&mut self, div: u16, cycles: u64) -> bool {
+        let mut interrupt = false;
+        let mut div = div;
+        let mut left = cycles;
+        while left > 0 {
+            // First cycle is always run normally, in case the divider was just reset
+            if left < cycles {
+                let idle = self.idle_cycles(div).min(left);
+                div = div.wrapping_add(idle as u16);
+                self.sync_counter += idle as u32;
+                self.last_div_bit = div & 0b1_0000_0000 > 0;
+                left -= idle;
+                if left == 0 {
+                    break;
+                }
+            }
+            div = div.wrapping_add(1);
+            self.cycle(div);
+            interrupt |= self.request_interrupt;
+            left -= 1;
+        }
+        interrupt
+    }
+
+    /// Returns the amount of upcoming T-cycles where the serial port does nothing
+    fn idle_cycles(&self, div: u16) -> u64 {
+        let until_sync = (SYNC_INTERVAL - self.sync_counter) as u64;
+        if self.control.intersects(SerialControl::TRANSFER_ENABLE) {
+            until_sync.min(Self::cycles_until_tick(div)) - 1
+        } else {
+            until_sync - 1
+        }
+    }
+
+    /// Returns the amount of T-cycles until the internal clock ticks
+    fn cycles_until_tick(div: u16) -> u64 {
+        0x200 - (div as u64 & 0x1FF)
+    }
+
+    /// Returns the amount of T-cycles until the serial port might request an interrupt
+    /// or sync the connected device, given the current divider value
+    pub fn next_event(&self, div: u16) -> u64 {
+        // Divider was just reset, so the clock might tick right away
+        if self.last_div_bit != (div & 0b1_0000_0000 > 0) {
+            return 1;
+        }
+        self.idle_cycles(div) + 1
+    }
+
+    /// Cycles the serial port forward by one T-cycle
+    fn cycle(&mut self, timer_div: u16) {
+        self.request_interrupt = false;
+
+        self.sync_counter += 1;
+        if self.sync_counter == SYNC_INTERVAL {
+            self.sync_counter = 0;
+            if let Some(device) = self.device.as_mut() {
+                device.sync();
+            }
+        }
+
+        // Internal clock runs at 8192 Hz,
+        // ticking on the falling edge of divider bit 8
+        let div_bit = timer_div & 0b1_0000_0000 > 0;
+        let clock_tick = self.last_div_bit && !div_bit;
+        self.last_div_bit = div_bit;
+
+        if !clock_tick || !self.control.intersects(SerialControl::TRANSFER_ENABLE) {
+            return;
+        }
+
+        if self.control.intersects(SerialControl::INTERNAL_CLOCK) {
+            // Shift one bit out of SB and one bit in from the other end
+            self.data = (self.data << 1) | (self.incoming >> 7);
+            self.incoming <<= 1;
+            self.bits_left -= 1;
+            if self.bits_left == 0 {
+                self.finish_transfer();
+            }
+        } else if let Some(device) = self.device.as_mut() {
+            // With an external clock the other end decides when the transfer happens,
+            // and the whole byte is received at once
+            if let Some(byte) = device.poll_external(self.data) {
+                self.data = byte;
+                self.finish_transfer();
+            }
+        }
+    }
+
+    fn waiting_for_external(&self) -> bool {
+        self.control.intersects(SerialControl::TRANSFER_ENABLE)
+            && !self.control.intersects(SerialControl::INTERNAL_CLOCK)
+    }
+
+    fn start_transfer(&mut self) {
+        self.bits_left = 8;
+        self.incoming = match self.device.as_mut() {
+            Some(device) => device.transfer(self.data),
+            // Nothing connected, so 1s are shifted in
+            None => 0xFF,
+        };
+    }
+
+    fn finish_transfer(&mut self) {
+        self.control.remove(SerialControl::TRANSFER_ENABLE);
+        self.request_interrupt = true;
+    }
+}
+
+impl MemoryAccess for Serial {
+    fn get_range(&self) -> Vec<RangeInclusive<u16>> {
+        vec![0xFF01..=0xFF02]
+    }
+
+    fn mem_read(&self, address: u16) -> u8 {
+        match address {
+            0xFF01 => self.data,
+            // Unused bits read as 1
+            0xFF02 => self.control.bits() | 0b0111_1110,
+            _ => unreachable!(),
+        }
+    }
+
+    fn mem_write(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF01 => self.data = value,
+            0xFF02 => {
+                let was_waiting = self.waiting_for_external();
+                self.control = SerialControl::from_bits_truncate(value);
+                if was_waiting && !self.waiting_for_external() {
+                    if let Some(device) = self.device.as_mut() {
+                        device.cancel_external();
+                    }
+                }
+                if self
+                    .control
+                    .contains(SerialControl::TRANSFER_ENABLE | SerialControl::INTERNAL_CLOCK)
+                {
+                    self.start_transfer();
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
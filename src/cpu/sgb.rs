@@ -0,0 +1,432 @@
+use super::*;
+
+/// Size of the Super Game Boy screen, which has the game in the middle of the border
+pub const SGB_WIDTH: usize = 256;
+pub const SGB_HEIGHT: usize = 224;
+/// Position of the game screen inside the border
+const SCREEN_X: usize = 48;
+const SCREEN_Y: usize = 40;
+/// Game screen is colored in 8x8 cells
+const CELLS_X: usize = 20;
+const CELLS_Y: usize = 18;
+/// Amount of attribute files that ATTR_TRN transfers
+const ATTRIBUTE_FILES: usize = 45;
+/// Colors the Super Game Boy starts with before a game sets its own palettes
+const DEFAULT_PALETTE: [u16; 4] = [0x67BF, 0x265B, 0x10B5, 0x2866];
+
+/// Colors of the whole Super Game Boy screen as RGB555, indexed with `[x][y]`
+pub type SGBMatrix = [[u16; SGB_HEIGHT]; SGB_WIDTH];
+
+mod command {
+    pub const PAL01: u8 = 0x00;
+    pub const PAL23: u8 = 0x01;
+    pub const PAL03: u8 = 0x02;
+    pub const PAL12: u8 = 0x03;
+    pub const ATTR_BLK: u8 = 0x04;
+    pub const ATTR_LIN: u8 = 0x05;
+    pub const ATTR_DIV: u8 = 0x06;
+    pub const ATTR_CHR: u8 = 0x07;
+    pub const PAL_SET: u8 = 0x0A;
+    pub const PAL_TRN: u8 = 0x0B;
+    pub const MLT_REQ: u8 = 0x11;
+    pub const CHR_TRN: u8 = 0x13;
+    pub const PCT_TRN: u8 = 0x14;
+    pub const ATTR_TRN: u8 = 0x15;
+    pub const ATTR_SET: u8 = 0x16;
+    pub const MASK_EN: u8 = 0x17;
+}
+
+/// What is shown instead of the game while the screen is masked with MASK_EN
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum ScreenMask {
+    None,
+    /// Last frame is kept on the screen
+    Freeze,
+    Black,
+    /// Screen is filled with color 0
+    Color0,
+}
+
+/// Data that is read from VRAM on the next VBlank after a transfer command
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    /// Border tiles $00-$7F, or $80-$FF if true
+    BorderTiles(bool),
+    /// Border tile map and palettes
+    BorderMap,
+    SystemPalettes,
+    AttributeFiles,
+}
+
+fn empty_screen() -> DisplayMatrix {
+    [[0; 144]; 160]
+}
+
+/// The Super Game Boy, which colors the screen and draws a border around it
+/// based on commands the game sends through the joypad register
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize)]
+pub struct SGB {
+    /// If the game runs on a Super Game Boy
+    pub enabled: bool,
+    /// If the game supports the Super Game Boy. Commands from other games are ignored
+    pub accepts_commands: bool,
+    /// Packets of the command that is being received
+    command: Vec<u8>,
+    /// Palettes the game screen is colored with
+    palettes: [[u16; 4]; 4],
+    /// Palette of each 8x8 cell of the game screen
+    #[serde(with = "BigArray")]
+    attributes: [u8; CELLS_X * CELLS_Y],
+    /// Palettes that can be picked with PAL_SET
+    #[serde(with = "BigArray")]
+    system_palettes: [u16; 512 * 4],
+    /// Attribute files that can be picked with ATTR_SET, 4 cells in a byte
+    #[serde(with = "BigArray")]
+    attribute_files: [u8; ATTRIBUTE_FILES * 90],
+    mask: ScreenMask,
+    pending_transfer: Option<Transfer>,
+    /// 256 tiles of 4 bits per pixel
+    #[serde(with = "BigArray")]
+    border_tiles: [u8; 0x2000],
+    /// 32x28 tile map of the border
+    #[serde(with = "BigArray")]
+    border_map: [u16; 32 * 28],
+    /// Border palettes 4-7
+    border_palettes: [[u16; 16]; 4],
+    /// Colored game screen
+    #[serde(skip)]
+    #[serde(default = "empty_screen")]
+    pub screen: DisplayMatrix,
+}
+
+impl SGB {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            accepts_commands: false,
+            command: vec![],
+            palettes: [DEFAULT_PALETTE; 4],
+            attributes: [0; CELLS_X * CELLS_Y],
+            system_palettes: [0; 512 * 4],
+            attribute_files: [0; ATTRIBUTE_FILES * 90],
+            mask: ScreenMask::None,
+            pending_transfer: None,
+            border_tiles: [0; 0x2000],
+            border_map: [0; 32 * 28],
+            border_palettes: [[0; 16]; 4],
+            screen: empty_screen(),
+        }
+    }
+
+    /// Adds a packet to the command that is being received.
+    /// Returns the whole command once all of its packets have been received
+    pub fn receive_packet(&mut self, packet: [u8; 16]) -> Option<Vec<u8>> {
+        if !self.accepts_commands {
+            return None;
+        }
+        self.command.extend_from_slice(&packet);
+        // Lower 3 bits of the first byte tell the amount of packets
+        let length = (self.command[0] & 0b111).max(1) as usize;
+        if self.command.len() < length * 16 {
+            return None;
+        }
+        Some(std::mem::take(&mut self.command))
+    }
+
+    /// Executes a command apart from MLT_REQ, which is handled by the joypad
+    pub fn execute(&mut self, data: &[u8]) {
+        match data[0] >> 3 {
+            command::PAL01 => self.set_palette_pair(0, 1, data),
+            command::PAL23 => self.set_palette_pair(2, 3, data),
+            command::PAL03 => self.set_palette_pair(0, 3, data),
+            command::PAL12 => self.set_palette_pair(1, 2, data),
+            command::ATTR_BLK => self.attribute_blocks(data),
+            command::ATTR_LIN => self.attribute_lines(data),
+            command::ATTR_DIV => self.attribute_division(data),
+            command::ATTR_CHR => self.attribute_cells(data),
+            command::PAL_SET => self.set_system_palettes(data),
+            command::PAL_TRN => self.pending_transfer = Some(Transfer::SystemPalettes),
+            command::CHR_TRN => {
+                self.pending_transfer = Some(Transfer::BorderTiles(data[1] & 0b1 > 0))
+            }
+            command::PCT_TRN => self.pending_transfer = Some(Transfer::BorderMap),
+            command::ATTR_TRN => self.pending_transfer = Some(Transfer::AttributeFiles),
+            command::ATTR_SET => {
+                self.set_attribute_file(data[1] & 0x3F);
+                if data[1] & 0b0100_0000 > 0 {
+                    self.mask = ScreenMask::None;
+                }
+            }
+            command::MASK_EN => {
+                self.mask = match data[1] & 0b11 {
+                    1 => ScreenMask::Freeze,
+                    2 => ScreenMask::Black,
+                    3 => ScreenMask::Color0,
+                    _ => ScreenMask::None,
+                }
+            }
+            command => eprintln!("Super Game Boy command {command:#04X} isn't supported"),
+        }
+    }
+
+    /// Sets colors of two palettes. Color 0 is shared between all palettes
+    fn set_palette_pair(&mut self, a: usize, b: usize, data: &[u8]) {
+        let color = |i: usize| u16::from_le_bytes([data[1 + i * 2], data[2 + i * 2]]) & 0x7FFF;
+        for palette in &mut self.palettes {
+            palette[0] = color(0);
+        }
+        for i in 1..4 {
+            self.palettes[a][i] = color(i);
+            self.palettes[b][i] = color(i + 3);
+        }
+    }
+
+    /// Copies palettes from the ones transferred with PAL_TRN
+    fn set_system_palettes(&mut self, data: &[u8]) {
+        for (palette, bytes) in data[1..9].chunks(2).enumerate() {
+            let index = (u16::from_le_bytes([bytes[0], bytes[1]]) & 0x1FF) as usize;
+            self.palettes[palette].copy_from_slice(&self.system_palettes[index * 4..index * 4 + 4]);
+        }
+        let color0 = self.palettes[0][0];
+        for palette in &mut self.palettes {
+            palette[0] = color0;
+        }
+        let attributes = data[9];
+        if attributes & 0b1000_0000 > 0 {
+            self.set_attribute_file(attributes & 0x3F);
+        }
+        if attributes & 0b0100_0000 > 0 {
+            self.mask = ScreenMask::None;
+        }
+    }
+
+    fn set_attribute_file(&mut self, file: u8) {
+        let file = file as usize;
+        if file >= ATTRIBUTE_FILES {
+            return;
+        }
+        for (cell, attribute) in self.attributes.iter_mut().enumerate() {
+            let byte = self.attribute_files[file * 90 + cell / 4];
+            // First cell is in the highest bits
+            *attribute = (byte >> (6 - (cell % 4) * 2)) & 0b11;
+        }
+    }
+
+    fn set_attribute(&mut self, x: usize, y: usize, palette: u8) {
+        if x < CELLS_X && y < CELLS_Y {
+            self.attributes[y * CELLS_X + x] = palette & 0b11;
+        }
+    }
+
+    /// Colors rectangles with separate palettes for the inside, the outline and the outside
+    fn attribute_blocks(&mut self, data: &[u8]) {
+        let count = (data[1] & 0x1F) as usize;
+        for block in data[2..].chunks_exact(6).take(count) {
+            let control = block[0] & 0b111;
+            let inside = block[1] & 0b11;
+            let outside = (block[1] >> 4) & 0b11;
+            // Changing only the inside or only the outside changes the outline with it
+            let (change_outline, outline) = match control {
+                0b001 => (true, inside),
+                0b100 => (true, outside),
+                _ => (control & 0b010 > 0, (block[1] >> 2) & 0b11),
+            };
+            let (x1, y1) = ((block[2] & 0x1F) as usize, (block[3] & 0x1F) as usize);
+            let (x2, y2) = ((block[4] & 0x1F) as usize, (block[5] & 0x1F) as usize);
+            for y in 0..CELLS_Y {
+                for x in 0..CELLS_X {
+                    let within = (x1..=x2).contains(&x) && (y1..=y2).contains(&y);
+                    let on_outline = within && (x == x1 || x == x2 || y == y1 || y == y2);
+                    if on_outline {
+                        if change_outline {
+                            self.set_attribute(x, y, outline);
+                        }
+                    } else if within {
+                        if control & 0b001 > 0 {
+                            self.set_attribute(x, y, inside);
+                        }
+                    } else if control & 0b100 > 0 {
+                        self.set_attribute(x, y, outside);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Colors whole rows or columns of cells
+    fn attribute_lines(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for &line in data[2..].iter().take(count) {
+            let position = (line & 0x1F) as usize;
+            let palette = (line >> 5) & 0b11;
+            if line & 0b1000_0000 > 0 {
+                for x in 0..CELLS_X {
+                    self.set_attribute(x, position, palette);
+                }
+            } else {
+                for y in 0..CELLS_Y {
+                    self.set_attribute(position, y, palette);
+                }
+            }
+        }
+    }
+
+    /// Divides the screen in two with a line, coloring both sides and the line
+    fn attribute_division(&mut self, data: &[u8]) {
+        let after = data[1] & 0b11;
+        let before = (data[1] >> 2) & 0b11;
+        let on_line = (data[1] >> 4) & 0b11;
+        let horizontal = data[1] & 0b0100_0000 > 0;
+        let line = (data[2] & 0x1F) as usize;
+        for y in 0..CELLS_Y {
+            for x in 0..CELLS_X {
+                let position = if horizontal { y } else { x };
+                let palette = match position.cmp(&line) {
+                    std::cmp::Ordering::Less => before,
+                    std::cmp::Ordering::Equal => on_line,
+                    std::cmp::Ordering::Greater => after,
+                };
+                self.set_attribute(x, y, palette);
+            }
+        }
+    }
+
+    /// Colors cells one by one, starting from given cell
+    fn attribute_cells(&mut self, data: &[u8]) {
+        let (mut x, mut y) = ((data[1] & 0x1F) as usize, (data[2] & 0x1F) as usize);
+        let count = (u16::from_le_bytes([data[3], data[4]]) as usize).min(CELLS_X * CELLS_Y);
+        let vertical = data[5] & 0b1 > 0;
+        for i in 0..count {
+            let Some(byte) = data.get(6 + i / 4) else {
+                break;
+            };
+            let palette = (byte >> (6 - (i % 4) * 2)) & 0b11;
+            self.set_attribute(x, y, palette);
+            // Cells wrap to the next row or column at the edge of the screen
+            if vertical {
+                y += 1;
+                if y == CELLS_Y {
+                    y = 0;
+                    x = (x + 1) % CELLS_X;
+                }
+            } else {
+                x += 1;
+                if x == CELLS_X {
+                    x = 0;
+                    y = (y + 1) % CELLS_Y;
+                }
+            }
+        }
+    }
+
+    /// Stores data sent with a transfer command
+    fn finish_transfer(&mut self, transfer: Transfer, data: &[u8]) {
+        let word = |i: usize| u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+        match transfer {
+            Transfer::BorderTiles(upper) => {
+                let start = if upper { 0x1000 } else { 0 };
+                self.border_tiles[start..start + 0x1000].copy_from_slice(&data[..0x1000]);
+            }
+            Transfer::BorderMap => {
+                for (i, entry) in self.border_map.iter_mut().enumerate() {
+                    *entry = word(i);
+                }
+                for (i, palette) in self.border_palettes.iter_mut().enumerate() {
+                    for (j, color) in palette.iter_mut().enumerate() {
+                        *color = word(0x400 + i * 16 + j) & 0x7FFF;
+                    }
+                }
+            }
+            Transfer::SystemPalettes => {
+                for (i, color) in self.system_palettes.iter_mut().enumerate() {
+                    *color = word(i) & 0x7FFF;
+                }
+            }
+            Transfer::AttributeFiles => {
+                let size = self.attribute_files.len();
+                self.attribute_files.copy_from_slice(&data[..size]);
+            }
+        }
+    }
+
+    /// Finishes pending transfers and colors the frame the PPU just finished
+    pub fn vblank(&mut self, ppu: &PPU) {
+        if let Some(transfer) = self.pending_transfer.take() {
+            self.finish_transfer(transfer, &ppu.screen_tile_data());
+        }
+        match self.mask {
+            ScreenMask::None => {
+                for (x, column) in self.screen.iter_mut().enumerate() {
+                    for (y, pixel) in column.iter_mut().enumerate() {
+                        let palette = self.attributes[(y / 8) * CELLS_X + x / 8];
+                        *pixel = self.palettes[palette as usize][ppu.display[x][y] as usize];
+                    }
+                }
+            }
+            ScreenMask::Freeze => {}
+            ScreenMask::Black => self.screen = [[0; 144]; 160],
+            ScreenMask::Color0 => self.screen = [[self.palettes[0][0]; 144]; 160],
+        }
+    }
+
+    /// Returns the color of the border at given coordinates
+    fn border_pixel(&self, x: usize, y: usize) -> u16 {
+        let entry = self.border_map[(y / 8) * 32 + x / 8];
+        let tile = (entry & 0xFF) as usize;
+        let palette = ((entry >> 10) & 0b11) as usize;
+        let tile_x = if entry & 0x4000 > 0 { 7 - x % 8 } else { x % 8 };
+        let tile_y = if entry & 0x8000 > 0 { 7 - y % 8 } else { y % 8 };
+        // Tiles have two pairs of bitplanes, which are 16 bytes apart
+        let row = tile * 32 + tile_y * 2;
+        let bit = 7 - tile_x;
+        let color = [row, row + 1, row + 16, row + 17]
+            .iter()
+            .enumerate()
+            .fold(0, |color, (plane, &i)| {
+                color | (((self.border_tiles[i] >> bit) & 1) << plane)
+            });
+        // Color 0 is transparent, which shows the shared background color
+        if color == 0 {
+            self.palettes[0][0]
+        } else {
+            self.border_palettes[palette][color as usize]
+        }
+    }
+
+    /// Returns the whole Super Game Boy screen, with the colored game screen inside the border
+    pub fn render(&self) -> Box<SGBMatrix> {
+        let mut matrix = Box::new([[0; SGB_HEIGHT]; SGB_WIDTH]);
+        for (x, column) in matrix.iter_mut().enumerate() {
+            for (y, pixel) in column.iter_mut().enumerate() {
+                let screen_x = x.wrapping_sub(SCREEN_X);
+                let screen_y = y.wrapping_sub(SCREEN_Y);
+                *pixel = if screen_x < 160 && screen_y < 144 {
+                    self.screen[screen_x][screen_y]
+                } else {
+                    self.border_pixel(x, y)
+                };
+            }
+        }
+        matrix
+    }
+}
+
+impl CPU {
+    /// Handles a packet the game sent to the Super Game Boy
+    pub fn sgb_packet(&mut self, packet: [u8; 16]) {
+        let Some(command) = self.sgb.receive_packet(packet) else {
+            return;
+        };
+        if command[0] >> 3 == command::MLT_REQ {
+            let players = match command[1] & 0b11 {
+                1 => 2,
+                3 => 4,
+                _ => 1,
+            };
+            self.input.set_players(players);
+        } else {
+            self.sgb.execute(&command);
+        }
+    }
+}
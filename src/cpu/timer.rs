@@ -42,8 +42,71 @@ impl Timer {
         }
     }
 
-    // Cycles the timer forward by one T-cycle
-    pub fn cycle(&mut self) {
+    /// Cycles the timer forward by given amount of T-cycles,
+    /// skipping over the cycles where only the divider is incremented.
+    /// Returns true if an interrupt was requested
+    pub fn run(&mut self, cycles: u64) -> bool {
+        let mut interrupt = false;
+        let mut left = cycles;
+        while left > 0 {
+            // First cycle is always run normally, so the AND result is up to date
+            // after registers have been written to
+            if left < cycles && self.overflow_delay < 0 {
+                let idle = self.idle_cycles().min(left);
+                self.div = self.div.wrapping_add(idle as u16);
+                self.previous_and = self.and_result();
+                left -= idle;
+                if left == 0 {
+                    break;
+                }
+            }
+            self.cycle();
+            interrupt |= self.request_interrupt;
+            left -= 1;
+        }
+        interrupt
+    }
+
+    /// Returns the AND between the selected divider bit and the enabled flag
+    fn and_result(&self) -> bool {
+        self.enabled && (self.div >> self.div_bit) & 0b1 > 0
+    }
+
+    /// Returns the amount of T-cycles until the divider bit selected for TIMA falls
+    fn cycles_until_increment(&self) -> u64 {
+        let period = 1u64 << (self.div_bit + 1);
+        period - (self.div as u64 & (period - 1))
+    }
+
+    /// Returns the amount of upcoming T-cycles where only the divider is incremented
+    fn idle_cycles(&self) -> u64 {
+        if self.enabled {
+            self.cycles_until_increment() - 1
+        } else {
+            u64::MAX
+        }
+    }
+
+    /// Returns the amount of T-cycles until the timer might request an interrupt
+    pub fn next_event(&self) -> u64 {
+        if self.overflow_delay >= 0 {
+            return self.overflow_delay as u64 + 1;
+        }
+        // Registers were just written, so TIMA might be incremented right away
+        if self.previous_and != self.and_result() {
+            return 1;
+        }
+        if !self.enabled {
+            return u64::MAX;
+        }
+        // TMA is written to TIMA 4 T-cycles after the increment that overflows it
+        let period = 1u64 << (self.div_bit + 1);
+        let increments = 0x100 - self.tima as u64;
+        self.cycles_until_increment() + (increments - 1) * period + 4
+    }
+
+    /// Cycles the timer forward by one T-cycle
+    fn cycle(&mut self) {
         self.request_interrupt = false;
 
         // Simulate the 4 T-cycle delay after overflowing
@@ -0,0 +1,264 @@
+use super::cpu::{
+    boot::BOOT_ROM_SIZE,
+    camera::ImageSource,
+    colorization::Colorization,
+    input::InputFlag,
+    memory::{CartridgeInfo, MBCType},
+    ppu::{DisplayMatrix, LayerMatrix},
+    scheduler::Component,
+    serial::SerialDevice,
+    sgb::{SGBMatrix, SGB_HEIGHT, SGB_WIDTH},
+    CPU,
+};
+use image::{Rgb, RgbImage};
+use std::fmt;
+
+/// Shades used for the four DMG color IDs when exporting the display without a palette
+pub const DMG_SHADES: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+
+/// Converts an RGB555 color of the CGB into 8-bit RGB channels
+pub fn rgb555_to_rgb(color: u16) -> [u8; 3] {
+    let channel = |shift: u16| {
+        let value = ((color >> shift) & 0x1F) as u8;
+        // Repeat the upper bits so that full intensity maps to 255
+        (value << 3) | (value >> 2)
+    };
+    [channel(0), channel(5), channel(10)]
+}
+
+/// Core options that affect how the emulated system is initialized
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulatorOptions {
+    /// Sample rate of the audio buffer returned by `Emulator::take_audio`
+    pub audio_sample_rate: u32,
+    /// Contents of a boot ROM file (e.g. `dmg_boot.bin`).
+    /// The built-in replacement boot ROM is used if not given
+    pub boot_rom: Option<Vec<u8>>,
+    /// How DMG games are colored
+    pub colorization: Colorization,
+    /// If DMG games are run on a Super Game Boy
+    pub sgb: bool,
+}
+
+impl Default for EmulatorOptions {
+    fn default() -> Self {
+        Self {
+            audio_sample_rate: 48000,
+            boot_rom: None,
+            colorization: Colorization::Off,
+            sgb: false,
+        }
+    }
+}
+
+/// Errors that can happen when loading a ROM file
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// ROM is too small to contain a cartridge header
+    TooSmall(usize),
+    /// Boot ROM isn't the size of the DMG boot ROM
+    InvalidBootRom(usize),
+    /// Cartridge type byte in the header is unknown or its mapper isn't emulated
+    UnsupportedCartridge(u8),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::TooSmall(len) => write!(
+                f,
+                "ROM is only {len} bytes long, which is too small to contain a cartridge header"
+            ),
+            LoadError::InvalidBootRom(len) => write!(
+                f,
+                "Boot ROM is {len} bytes long, but the DMG boot ROM is {BOOT_ROM_SIZE} bytes"
+            ),
+            LoadError::UnsupportedCartridge(byte) => {
+                write!(f, "Cartridge type {byte:#04X} isn't supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Frontend-agnostic wrapper around the emulated system.
+/// Doesn't handle any windowing, audio playback or timing,
+/// so it can be driven by anything from the GUI app to tests.
+pub struct Emulator {
+    pub cpu: CPU,
+    pub options: EmulatorOptions,
+}
+
+impl Emulator {
+    /// Initializes a new emulator running the given ROM file
+    pub fn new(rom_file: Vec<u8>, options: EmulatorOptions) -> Result<Self, LoadError> {
+        Self::validate_rom(&rom_file)?;
+        if let Some(boot_rom) = &options.boot_rom {
+            if boot_rom.len() != BOOT_ROM_SIZE {
+                return Err(LoadError::InvalidBootRom(boot_rom.len()));
+            }
+        }
+        Ok(Self {
+            cpu: CPU::new(rom_file, &options),
+            options,
+        })
+    }
+
+    fn validate_rom(rom_file: &[u8]) -> Result<(), LoadError> {
+        // Cartridge header ends at $014F
+        if rom_file.len() < 0x0150 {
+            return Err(LoadError::TooSmall(rom_file.len()));
+        }
+        match CartridgeInfo::from_rom(rom_file).mbc {
+            MBCType::Unknown(byte) => Err(LoadError::UnsupportedCartridge(byte)),
+            MBCType::MBC6 => Err(LoadError::UnsupportedCartridge(0x20)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces the running system with a fresh one running the given ROM file
+    pub fn load_rom(&mut self, rom_file: Vec<u8>) -> Result<(), LoadError> {
+        Self::validate_rom(&rom_file)?;
+        let device = self.cpu.serial.device.take();
+        let camera_source = self.cpu.mem.mbc.camera.source.take();
+        self.cpu = CPU::new(rom_file, &self.options);
+        // Keep the link cable plugged in and the camera pointed at the same source
+        self.cpu.serial.device = device;
+        self.cpu.mem.mbc.camera.source = camera_source;
+        Ok(())
+    }
+
+    /// Runs emulation until the start of the next VBlank,
+    /// or for the length of a frame while the LCD is off
+    pub fn run_frame(&mut self) {
+        // Execution function returns true when a frame ended
+        while !self.cpu.execute() {}
+    }
+
+    /// Executes a single instruction.
+    /// Returns whether or not a frame ended during execution
+    pub fn step_instruction(&mut self) -> bool {
+        self.cpu.execute()
+    }
+
+    /// Updates the state of the joypad.
+    /// Flags set in `pressed` are the buttons that are currently held down
+    pub fn set_input(&mut self, pressed: InputFlag) {
+        // Joypad register uses 0 to mark a pressed button
+        self.cpu.update_input(&pressed.complement());
+    }
+
+    /// Updates the joypad of another player when the Super Game Boy has multiplayer enabled.
+    /// Players are numbered from 2 to 4, as player 1 uses `set_input`
+    pub fn set_player_input(&mut self, player: u8, pressed: InputFlag) {
+        if let 2..=4 = player {
+            self.cpu.input.other_players[player as usize - 2] = pressed.complement();
+        }
+    }
+
+    /// Returns whether the running game has been started in CGB mode
+    pub fn is_cgb(&self) -> bool {
+        self.cpu.ppu.cgb
+    }
+
+    /// Returns whether the game is running on a Super Game Boy
+    pub fn is_sgb(&self) -> bool {
+        self.cpu.sgb.enabled
+    }
+
+    /// Returns whether the framebuffer has RGB555 colors instead of 2-bit color IDs,
+    /// which is the case for CGB games, colorized DMG games and the Super Game Boy
+    pub fn has_color(&self) -> bool {
+        self.cpu.ppu.cgb || self.cpu.ppu.compat || self.cpu.sgb.enabled
+    }
+
+    /// Changes how DMG games are colored. Takes effect immediately
+    pub fn set_colorization(&mut self, colorization: Colorization) {
+        self.options.colorization = colorization;
+        if self.cpu.mem.info.cgb || self.cpu.sgb.enabled {
+            return;
+        }
+        match colorization.palettes(&self.cpu.mem.mbc.rom) {
+            Some(palettes) => self.cpu.ppu.load_compat_palettes(&palettes),
+            None => self.cpu.ppu.compat = false,
+        }
+    }
+
+    /// Returns the most recently drawn frame, indexed with `[x][y]`.
+    /// Pixels are 2-bit color IDs in DMG mode and RGB555 colors in CGB mode, on the Super Game Boy
+    /// or when colorized
+    pub fn framebuffer(&self) -> &DisplayMatrix {
+        if self.cpu.sgb.enabled {
+            &self.cpu.sgb.screen
+        } else {
+            &self.cpu.ppu.display
+        }
+    }
+
+    /// Returns the whole Super Game Boy screen as RGB555 colors, with the border around the game.
+    /// Returns None if the game isn't running on a Super Game Boy
+    pub fn sgb_screen(&self) -> Option<Box<SGBMatrix>> {
+        self.cpu.sgb.enabled.then(|| self.cpu.sgb.render())
+    }
+
+    /// Returns the layer (background / window, OBJ0 or OBJ1) each pixel
+    /// of the most recently drawn frame was drawn from, indexed with `[x][y]`.
+    /// Only meaningful for DMG games
+    pub fn layers(&self) -> &LayerMatrix {
+        &self.cpu.ppu.layers
+    }
+
+    /// Returns the most recently drawn frame as an image.
+    /// DMG games are exported in grayscale unless they're colorized.
+    /// On the Super Game Boy, the border is included
+    pub fn screenshot(&self) -> RgbImage {
+        if let Some(screen) = self.sgb_screen() {
+            return RgbImage::from_fn(SGB_WIDTH as u32, SGB_HEIGHT as u32, |x, y| {
+                Rgb(rgb555_to_rgb(screen[x as usize][y as usize]))
+            });
+        }
+        let display = self.framebuffer();
+        let color = self.has_color();
+        RgbImage::from_fn(160, 144, |x, y| {
+            let pixel = display[x as usize][y as usize];
+            if color {
+                Rgb(rgb555_to_rgb(pixel))
+            } else {
+                let shade = DMG_SHADES[pixel as usize];
+                Rgb([shade, shade, shade])
+            }
+        })
+    }
+
+    /// Connects a device to the serial port, replacing the previous one
+    pub fn connect_serial(&mut self, device: Box<dyn SerialDevice>) {
+        self.cpu.serial.device = Some(device);
+    }
+
+    /// Disconnects and returns the device connected to the serial port
+    pub fn disconnect_serial(&mut self) -> Option<Box<dyn SerialDevice>> {
+        self.cpu.serial.device.take()
+    }
+
+    /// Sets where the Pocket Camera gets its images from.
+    /// A test pattern is used if this isn't called
+    pub fn connect_camera(&mut self, source: Box<dyn ImageSource>) {
+        self.cpu.mem.mbc.camera.source = Some(source);
+    }
+
+    /// Returns whether the rumble motor of the cartridge has been on since the last call.
+    /// Games pulse the motor rapidly to control its strength, so it's tracked between calls
+    pub fn take_rumble(&mut self) -> bool {
+        let mbc = &mut self.cpu.mem.mbc;
+        let rumbled = mbc.rumbled || mbc.rumble;
+        mbc.rumbled = false;
+        rumbled
+    }
+
+    /// Returns interleaved stereo samples generated since the last call, and empties the buffer
+    pub fn take_audio(&mut self) -> Vec<f32> {
+        self.cpu.sync(Component::APU);
+        self.cpu.apu.receive_buffer()
+    }
+}
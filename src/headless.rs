@@ -0,0 +1,183 @@
+//! Command-line runner that runs ROMs without opening a window or an audio device,
+//! for example on CI machines. Only uses the emulator core, so it builds without the gui feature
+
+use dmg_2025::{
+    camera::open_image_source,
+    cpu::{input::InputFlag, serial::StdoutLogger},
+    Emulator, EmulatorOptions,
+};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: dmg-headless <rom> [--frames <n>] [--screenshot <png>] [--input <script>] [--boot-rom <bin>] [--camera <png or folder>] [--serial-log] [--sgb]";
+
+/// Options for running a ROM without a window or audio device
+struct RunOptions {
+    rom_path: PathBuf,
+    frames: u32,
+    screenshot_path: Option<PathBuf>,
+    input_path: Option<PathBuf>,
+    boot_rom_path: Option<PathBuf>,
+    camera_path: Option<PathBuf>,
+    serial_log: bool,
+    sgb: bool,
+}
+
+impl RunOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut rom_path = None;
+        let mut frames = 60;
+        let mut screenshot_path = None;
+        let mut input_path = None;
+        let mut boot_rom_path = None;
+        let mut camera_path = None;
+        let mut serial_log = false;
+        let mut sgb = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--frames" => {
+                    let value = args.next().ok_or("Missing value for --frames")?;
+                    frames = value
+                        .parse()
+                        .map_err(|_| format!("Invalid frame count: {value}"))?;
+                }
+                "--screenshot" => {
+                    let value = args.next().ok_or("Missing value for --screenshot")?;
+                    screenshot_path = Some(PathBuf::from(value));
+                }
+                "--input" => {
+                    let value = args.next().ok_or("Missing value for --input")?;
+                    input_path = Some(PathBuf::from(value));
+                }
+                "--boot-rom" => {
+                    let value = args.next().ok_or("Missing value for --boot-rom")?;
+                    boot_rom_path = Some(PathBuf::from(value));
+                }
+                "--camera" => {
+                    let value = args.next().ok_or("Missing value for --camera")?;
+                    camera_path = Some(PathBuf::from(value));
+                }
+                "--serial-log" => serial_log = true,
+                "--sgb" => sgb = true,
+                _ if arg.starts_with("--") => return Err(format!("Unknown option: {arg}")),
+                _ if rom_path.is_none() => rom_path = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument: {arg}")),
+            }
+        }
+
+        Ok(Self {
+            rom_path: rom_path.ok_or("No ROM file given")?,
+            frames,
+            screenshot_path,
+            input_path,
+            boot_rom_path,
+            camera_path,
+            serial_log,
+            sgb,
+        })
+    }
+}
+
+/// Parses an input script.
+/// Each line has a frame number followed by the buttons that are held from that frame onwards,
+/// for example `120 A RIGHT`. A frame number without buttons releases everything.
+/// Empty lines and lines starting with `#` are ignored.
+fn parse_input_script(script: &str) -> Result<Vec<(u32, InputFlag)>, String> {
+    let mut events = vec![];
+    for (line_index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let frame_word = words.next().unwrap();
+        let frame = frame_word.parse::<u32>().map_err(|_| {
+            format!(
+                "Invalid frame number on line {}: {frame_word}",
+                line_index + 1
+            )
+        })?;
+        let mut pressed = InputFlag::empty();
+        for word in words {
+            let button = InputFlag::from_name(&word.to_uppercase())
+                .ok_or(format!("Invalid button on line {}: {word}", line_index + 1))?;
+            pressed.insert(button);
+        }
+        events.push((frame, pressed));
+    }
+    events.sort_by_key(|(frame, _)| *frame);
+    Ok(events)
+}
+
+fn run_with_options(options: RunOptions) -> Result<(), String> {
+    let rom_file = std::fs::read(&options.rom_path)
+        .map_err(|e| format!("Failed to read {}: {e}", options.rom_path.display()))?;
+    let input_events = match &options.input_path {
+        Some(path) => {
+            let script = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            parse_input_script(&script)?
+        }
+        None => vec![],
+    };
+
+    let boot_rom = match &options.boot_rom_path {
+        Some(path) => Some(
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    let emulator_options = EmulatorOptions {
+        boot_rom,
+        sgb: options.sgb,
+        ..Default::default()
+    };
+    let mut emulator = Emulator::new(rom_file, emulator_options)
+        .map_err(|e| format!("Failed to load ROM: {e}"))?;
+    if options.serial_log {
+        emulator.connect_serial(Box::new(StdoutLogger));
+    }
+    if let Some(path) = &options.camera_path {
+        emulator.connect_camera(open_image_source(path)?);
+    }
+
+    let mut input_events = input_events.into_iter().peekable();
+    for frame in 0..options.frames {
+        while let Some((_, pressed)) = input_events.next_if(|(start, _)| *start <= frame) {
+            emulator.set_input(pressed);
+        }
+        emulator.run_frame();
+        // There's no audio device, so just throw the samples away
+        emulator.take_audio();
+    }
+
+    if let Some(path) = &options.screenshot_path {
+        emulator
+            .screenshot()
+            .save(path)
+            .map_err(|e| format!("Failed to save screenshot to {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs a ROM for a set amount of frames without opening a window or an audio device
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match RunOptions::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run_with_options(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Core of the DMG-2025 emulator.
+//! Contains the emulated system without any windowing or audio playback,
+//! so it can be embedded into tools, tests and other frontends.
+// Components are always constructed with explicit initial state through new()
+#![allow(clippy::new_without_default)]
+
+pub mod camera;
+pub mod cpu;
+pub mod emulator;
+pub mod link;
+pub mod printer;
+pub use emulator::*;
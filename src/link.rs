@@ -0,0 +1,323 @@
+use super::cpu::serial::SerialDevice;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Condvar, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Port used for link cable sessions when none is specified
+pub const DEFAULT_LINK_PORT: u16 = 7654;
+
+/// Messages sent over the link cable. Each message is two bytes long
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Message {
+    /// Sender has emulated another frame's worth of cycles
+    Sync,
+    /// Sender clocked a transfer with its internal clock, and waits for a `Reply`
+    Transfer(u8),
+    /// Byte shifted out of the sender during the transfer the other end clocked
+    Reply(u8),
+}
+
+impl Message {
+    fn encode(self) -> [u8; 2] {
+        match self {
+            Message::Sync => [0, 0],
+            Message::Transfer(byte) => [1, byte],
+            Message::Reply(byte) => [2, byte],
+        }
+    }
+
+    fn decode(bytes: [u8; 2]) -> Option<Self> {
+        match bytes[0] {
+            0 => Some(Message::Sync),
+            1 => Some(Message::Transfer(bytes[1])),
+            2 => Some(Message::Reply(bytes[1])),
+            _ => None,
+        }
+    }
+}
+
+/// How long a transfer waits for the other end to answer before giving up.
+/// Other end answers by the end of its current frame at the latest,
+/// so this only runs out if it's paused or stuck
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Listens for another instance that wants to join a link cable session
+pub struct LinkHost {
+    listener: TcpListener,
+}
+
+impl LinkHost {
+    /// Starts listening on given port on localhost
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener })
+    }
+
+    /// Returns the port the host listens on, which is useful when binding to port 0
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Returns a connected link cable if another instance has joined
+    pub fn try_accept(&self) -> io::Result<Option<LinkCable>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Ok(Some(LinkCable::new(stream)?)),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// State of the session that both the emulator and the thread reading from the other end access
+#[derive(Default)]
+struct LinkState {
+    /// Frame syncs sent to the other end
+    syncs_sent: u64,
+    /// Frame syncs received from the other end
+    syncs_received: u64,
+    /// Value of SB while this end waits for an external clock
+    ready_byte: Option<u8>,
+    /// Set while this end clocks a transfer and waits for the other end to reply
+    transferring: bool,
+    /// Reply to the transfer this end clocked
+    reply: Option<u8>,
+    /// Byte from a transfer the other end clocked before this end was ready for it
+    pending: Option<u8>,
+    /// Byte received from a transfer the other end clocked while this end was ready
+    received: Option<u8>,
+}
+
+struct Shared {
+    state: Mutex<LinkState>,
+    /// Notified when the other end syncs, replies to a transfer or disconnects
+    changed: Condvar,
+    connected: AtomicBool,
+    /// Writing half of the socket, which both threads send messages through
+    writer: Mutex<TcpStream>,
+}
+
+impl Shared {
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    fn disconnect(&self, reason: &str) {
+        if self.connected.swap(false, Ordering::Relaxed) {
+            eprintln!("Link cable disconnected: {reason}");
+            let _ = self
+                .writer
+                .lock()
+                .unwrap()
+                .shutdown(std::net::Shutdown::Both);
+        }
+        self.changed.notify_all();
+    }
+
+    fn send(&self, message: Message) {
+        let result = self.writer.lock().unwrap().write_all(&message.encode());
+        if let Err(e) = result {
+            self.disconnect(&e.to_string());
+        }
+    }
+
+    /// Reads messages from the other end until the connection is closed
+    fn receive(&self, mut stream: TcpStream) {
+        let mut bytes = [0u8; 2];
+        while self.is_connected() {
+            if let Err(e) = stream.read_exact(&mut bytes) {
+                let reason = match e.kind() {
+                    ErrorKind::UnexpectedEof => "other end closed the connection".to_string(),
+                    _ => e.to_string(),
+                };
+                self.disconnect(&reason);
+                return;
+            }
+            let mut state = self.state.lock().unwrap();
+            match Message::decode(bytes) {
+                Some(Message::Sync) => {
+                    state.syncs_received += 1;
+                    self.changed.notify_all();
+                }
+                Some(Message::Transfer(byte)) => {
+                    let reply = if state.transferring {
+                        // Both ends are driving the clock, so neither one is listening
+                        Some(0xFF)
+                    } else if let Some(ready_byte) = state.ready_byte.take() {
+                        state.received = Some(byte);
+                        Some(ready_byte)
+                    } else {
+                        // Emulator answers once it starts waiting or the frame ends
+                        state.pending = Some(byte);
+                        None
+                    };
+                    drop(state);
+                    if let Some(reply) = reply {
+                        self.send(Message::Reply(reply));
+                    }
+                }
+                Some(Message::Reply(byte)) => {
+                    state.reply = Some(byte);
+                    self.changed.notify_all();
+                }
+                None => {
+                    drop(state);
+                    self.disconnect("received invalid data");
+                }
+            }
+        }
+    }
+}
+
+/// Handle to a link cable session that can be used while the cable is plugged into an emulator
+#[derive(Clone)]
+pub struct LinkConnection(Arc<Shared>);
+
+impl LinkConnection {
+    /// Returns true while the other end is connected
+    pub fn is_connected(&self) -> bool {
+        self.0.is_connected()
+    }
+
+    /// Waits for up to given duration for the other end to emulate as many frames as this one.
+    /// Returns true if this end can run the next frame.
+    /// Shouldn't be called while holding onto the emulator, so the rest of the app keeps running
+    pub fn wait_for_peer(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.0.state.lock().unwrap();
+        while self.is_connected() && state.syncs_received < state.syncs_sent {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self
+                .0
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+        true
+    }
+}
+
+/// Serial device that links two emulator instances over TCP.
+/// Both ends sync every frame's worth of cycles, and the frontend waits with
+/// `LinkConnection::wait_for_peer` so neither one runs ahead of the other.
+/// Within a frame, the end that clocks a transfer blocks until the other end replies
+/// with the byte it's shifting out for that exchange
+pub struct LinkCable {
+    shared: Arc<Shared>,
+}
+
+impl LinkCable {
+    /// Joins a session hosted on given port on localhost
+    pub fn join(port: u16) -> io::Result<Self> {
+        Self::new(TcpStream::connect((Ipv4Addr::LOCALHOST, port))?)
+    }
+
+    /// Starts a session over given stream, reading from the other end on a separate thread
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(false)?;
+        let reader = stream.try_clone()?;
+        let shared = Arc::new(Shared {
+            state: Mutex::new(LinkState::default()),
+            changed: Condvar::new(),
+            connected: AtomicBool::new(true),
+            writer: Mutex::new(stream),
+        });
+        let shared_ref = Arc::clone(&shared);
+        thread::spawn(move || shared_ref.receive(reader));
+        Ok(Self { shared })
+    }
+
+    /// Returns a handle to the session that stays usable after the cable is plugged in
+    pub fn connection(&self) -> LinkConnection {
+        LinkConnection(Arc::clone(&self.shared))
+    }
+}
+
+impl Drop for LinkCable {
+    fn drop(&mut self) {
+        self.shared.disconnect("unplugged");
+    }
+}
+
+impl SerialDevice for LinkCable {
+    /// Blocks until the other end replies with its byte.
+    /// If it doesn't reply within `TRANSFER_TIMEOUT`, $FF is shifted in like with nothing plugged in
+    fn transfer(&mut self, byte: u8) -> u8 {
+        if !self.shared.is_connected() {
+            return 0xFF;
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        state.transferring = true;
+        state.reply = None;
+        drop(state);
+        self.shared.send(Message::Transfer(byte));
+
+        let deadline = Instant::now() + TRANSFER_TIMEOUT;
+        let mut state = self.shared.state.lock().unwrap();
+        while state.reply.is_none() && self.shared.is_connected() {
+            let now = Instant::now();
+            if now >= deadline {
+                eprintln!("Link cable partner didn't answer a transfer");
+                break;
+            }
+            state = self
+                .shared
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+        state.transferring = false;
+        state.reply.take().unwrap_or(0xFF)
+    }
+
+    fn poll_external(&mut self, byte: u8) -> Option<u8> {
+        if !self.shared.is_connected() {
+            return None;
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(received) = state.received.take() {
+            return Some(received);
+        }
+        // Other end clocked a transfer before this end started waiting for it
+        if let Some(pending) = state.pending.take() {
+            drop(state);
+            self.shared.send(Message::Reply(byte));
+            return Some(pending);
+        }
+        // Keep the byte up to date, since SB can change while waiting
+        state.ready_byte = Some(byte);
+        None
+    }
+
+    fn cancel_external(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.ready_byte = None;
+        state.received = None;
+    }
+
+    fn sync(&mut self) {
+        if !self.shared.is_connected() {
+            return;
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        state.syncs_sent += 1;
+        // This end didn't wait for an external clock during the frame, so the data line stayed high
+        let unanswered = state.pending.take().is_some();
+        drop(state);
+        if unanswered {
+            self.shared.send(Message::Reply(0xFF));
+        }
+        self.shared.send(Message::Sync);
+    }
+}
@@ -2,21 +2,29 @@
     all(target_os = "windows", not(debug_assertions),),
     windows_subsystem = "windows"
 )]
+use std::process::ExitCode;
 use std::sync::Arc;
 
-mod cpu;
-use cpu::CPU;
+use dmg_2025::{cpu::CPU, rgb555_to_rgb, Emulator};
 mod window;
 use window::Window;
 mod options;
 use options::*;
 
-fn main() -> eframe::Result {
+fn main() -> ExitCode {
     // Display backtrace
     std::env::set_var("RUST_BACKTRACE", "1");
     // Log to stderr
     env_logger::init();
 
+    if let Err(e) = run_window() {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_window() -> eframe::Result {
     // Initialize main window
     let options = Options::load();
     let scale = options.window_scale;
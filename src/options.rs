@@ -1,10 +1,19 @@
 use egui::{Color32, Key};
 use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io::Write};
 
-use crate::cpu::input::InputFlag;
+use dmg_2025::{
+    camera::open_image_source,
+    cpu::{
+        camera::{ImageSource, TestPattern},
+        colorization::{Colorization, BUTTON_PALETTES},
+        input::InputFlag,
+        ppu::Layer,
+    },
+    EmulatorOptions,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 /// Represents color palette for display
@@ -108,14 +117,93 @@ impl<'de> Visitor<'de> for PaletteVisitor {
     }
 }
 
+/// Separate palettes for the background / window and both object palettes
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(from = "SavedLayerPalettes")]
+pub struct LayerPalettes {
+    pub bg: Palette,
+    pub obj0: Palette,
+    pub obj1: Palette,
+}
+
+/// Layer palettes as saved in the options file.
+/// Older versions saved a single palette, which is then used for every layer
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedLayerPalettes {
+    Layers {
+        bg: Palette,
+        obj0: Palette,
+        obj1: Palette,
+    },
+    Single(Palette),
+}
+
+impl From<SavedLayerPalettes> for LayerPalettes {
+    fn from(saved: SavedLayerPalettes) -> Self {
+        match saved {
+            SavedLayerPalettes::Layers { bg, obj0, obj1 } => Self { bg, obj0, obj1 },
+            SavedLayerPalettes::Single(palette) => Self::uniform(palette),
+        }
+    }
+}
+
+impl Default for LayerPalettes {
+    fn default() -> Self {
+        Self::uniform(Palette::original())
+    }
+}
+
+impl LayerPalettes {
+    /// Uses the same palette for every layer
+    pub fn uniform(palette: Palette) -> Self {
+        Self {
+            bg: palette.clone(),
+            obj0: palette.clone(),
+            obj1: palette,
+        }
+    }
+
+    pub fn get(&self, layer: Layer) -> &Palette {
+        match layer {
+            Layer::BG => &self.bg,
+            Layer::OBJ0 => &self.obj0,
+            Layer::OBJ1 => &self.obj1,
+        }
+    }
+
+    pub fn get_mut(&mut self, layer: Layer) -> &mut Palette {
+        match layer {
+            Layer::BG => &mut self.bg,
+            Layer::OBJ0 => &mut self.obj0,
+            Layer::OBJ1 => &mut self.obj1,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, PartialEq)]
 pub struct Options {
     pub data_path: String,
     pub rom_path: String,
+    /// Path to boot ROM file. Built-in boot ROM is used if empty
+    #[serde(default)]
+    pub boot_rom_path: String,
+    /// Path to an image or a folder of frames that the Pocket Camera sees.
+    /// Test pattern is used if empty
+    #[serde(default)]
+    pub camera_path: String,
     pub keybinds: HashMap<InputFlag, String>,
     pub window_scale: u8,
     pub palette_preset: u8,
-    pub custom_palette: Palette,
+    /// Older versions had a single custom palette, which is migrated into every layer
+    #[serde(default, alias = "custom_palette")]
+    pub custom_palettes: LayerPalettes,
+    /// How DMG games are colored. Palette is only used when this is off
+    #[serde(default)]
+    pub colorization: Colorization,
+    /// If DMG games are run on a Super Game Boy
+    #[serde(default)]
+    pub sgb: bool,
     pub audio_sample_rate: u32,
     pub volume: u8,
 }
@@ -131,7 +219,9 @@ impl Options {
         }
         let file = fs::read_to_string(options_path).unwrap();
         let json = serde_json::from_str(&file);
-        if let Ok(options) = json {
+        if let Ok(mut options) = json {
+            Self::add_missing_keybinds(&mut options);
+            Self::check_colorization(&mut options);
             options
         } else {
             eprintln!("Options file outdated or corrupted. Restoring defaults");
@@ -139,6 +229,79 @@ impl Options {
         }
     }
 
+    /// Returns the subset of options used by the emulator core
+    pub fn emulator_options(&self) -> EmulatorOptions {
+        EmulatorOptions {
+            audio_sample_rate: self.audio_sample_rate,
+            boot_rom: self.load_boot_rom(),
+            colorization: self.colorization,
+            sgb: self.sgb,
+        }
+    }
+
+    /// Reads the boot ROM file if one is set
+    fn load_boot_rom(&self) -> Option<Vec<u8>> {
+        if self.boot_rom_path.is_empty() {
+            return None;
+        }
+        fs::read(&self.boot_rom_path)
+            .inspect_err(|e| eprintln!("Failed to read boot ROM, using built-in one: {e}"))
+            .ok()
+    }
+
+    /// Returns the image source for the Pocket Camera
+    pub fn camera_source(&self) -> Box<dyn ImageSource> {
+        if self.camera_path.is_empty() {
+            return Box::new(TestPattern::new());
+        }
+        open_image_source(Path::new(&self.camera_path)).unwrap_or_else(|e| {
+            eprintln!("Failed to open camera image, using test pattern: {e}");
+            Box::new(TestPattern::new())
+        })
+    }
+
+    /// Returns the palettes that are currently in use
+    pub fn palettes(&self) -> LayerPalettes {
+        match self.palette_preset {
+            0 => LayerPalettes::uniform(Palette::original()),
+            1 => LayerPalettes::uniform(Palette::lcd()),
+            2 => self.custom_palettes.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the name of the current colorization setting
+    pub fn colorization_name(&self) -> &'static str {
+        match self.colorization {
+            Colorization::Off => "Off",
+            Colorization::Auto => "Auto",
+            Colorization::Preset(index) => BUTTON_PALETTES
+                .get(index)
+                .map_or("Unknown", |(name, _)| name),
+        }
+    }
+
+    /// Cycles colorization between off, automatic and the button combination palettes
+    pub fn cycle_colorization(&mut self, forward: bool) {
+        // Settings are numbered with off as 0 and auto as 1
+        let count = BUTTON_PALETTES.len() + 2;
+        let current = match self.colorization {
+            Colorization::Off => 0,
+            Colorization::Auto => 1,
+            Colorization::Preset(index) => index + 2,
+        };
+        let next = if forward {
+            (current + 1) % count
+        } else {
+            (current + count - 1) % count
+        };
+        self.colorization = match next {
+            0 => Colorization::Off,
+            1 => Colorization::Auto,
+            index => Colorization::Preset(index - 2),
+        };
+    }
+
     fn init_default() -> Self {
         let options = Options::default();
         options.save();
@@ -164,6 +327,25 @@ impl Options {
         let _ = fs::create_dir(folder.join("saves"));
     }
 
+    /// Binds inputs that were added after the options file was saved to their default keys
+    fn add_missing_keybinds(options: &mut Options) {
+        for (input, key) in Self::default_keybinds() {
+            options.keybinds.entry(input).or_insert(key);
+        }
+    }
+
+    /// Presets are saved as an index, which can be out of range if the file was edited
+    fn check_colorization(options: &mut Options) {
+        let preset_exists = match options.colorization {
+            Colorization::Preset(index) => index < BUTTON_PALETTES.len(),
+            _ => true,
+        };
+        if !preset_exists {
+            eprintln!("Colorization preset doesn't exist. Restoring default");
+            options.colorization = Colorization::default();
+        }
+    }
+
     pub fn default_keybinds() -> HashMap<InputFlag, String> {
         HashMap::from([
             (InputFlag::RIGHT, Key::ArrowRight.name().to_string()),
@@ -174,6 +356,10 @@ impl Options {
             (InputFlag::B, Key::Z.name().to_string()),
             (InputFlag::SELECT, Key::Backspace.name().to_string()),
             (InputFlag::START, Key::Enter.name().to_string()),
+            (InputFlag::TILT_RIGHT, Key::L.name().to_string()),
+            (InputFlag::TILT_LEFT, Key::J.name().to_string()),
+            (InputFlag::TILT_UP, Key::I.name().to_string()),
+            (InputFlag::TILT_DOWN, Key::K.name().to_string()),
         ])
     }
 }
@@ -188,10 +374,14 @@ impl Default for Options {
                 .unwrap()
                 .into(),
             rom_path: String::new(),
+            boot_rom_path: String::new(),
+            camera_path: String::new(),
             keybinds: Self::default_keybinds(),
             window_scale: 4,
             palette_preset: 0,
-            custom_palette: Palette::original(),
+            custom_palettes: LayerPalettes::default(),
+            colorization: Colorization::default(),
+            sgb: false,
             audio_sample_rate: 48000,
             volume: 100,
         }
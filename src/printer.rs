@@ -0,0 +1,277 @@
+use super::cpu::serial::SerialDevice;
+use std::sync::mpsc;
+
+/// Printer RAM holds up to 8 KiB of tile data
+const BUFFER_SIZE: usize = 0x2000;
+/// Printed images are always 20 tiles wide
+pub const PRINT_WIDTH: usize = 160;
+/// Amount of status inquiries the printer reports being busy for after starting a print
+const PRINT_BUSY_POLLS: u8 = 8;
+
+mod status {
+    pub const CHECKSUM_ERROR: u8 = 0b0000_0001;
+    pub const PRINTING: u8 = 0b0000_0010;
+    pub const IMAGE_DATA_FULL: u8 = 0b0000_0100;
+    pub const UNPROCESSED_DATA: u8 = 0b0000_1000;
+}
+
+mod command {
+    pub const INIT: u8 = 0x01;
+    pub const PRINT: u8 = 0x02;
+    pub const DATA: u8 = 0x04;
+    pub const STATUS: u8 = 0x0F;
+}
+
+/// A finished printout
+pub struct PrintedPage {
+    /// Height of the page in pixels. Width is always `PRINT_WIDTH`
+    pub height: usize,
+    /// Shades of the page from 0 (white) to 3 (black), row by row
+    pub pixels: Vec<u8>,
+}
+
+/// Which part of a packet the printer expects to receive next
+#[derive(Clone, Copy, PartialEq)]
+enum PacketState {
+    MagicByte1,
+    MagicByte2,
+    Command,
+    Compression,
+    LengthLow,
+    LengthHigh,
+    Data,
+    ChecksumLow,
+    ChecksumHigh,
+    /// Printer answers with 0x81 to tell that it's connected
+    KeepAlive,
+    /// Printer answers with its status
+    Status,
+}
+
+/// Serial device that emulates the Game Boy Printer.
+/// Finished pages are sent to the receiver returned by `Printer::new`
+pub struct Printer {
+    state: PacketState,
+    command: u8,
+    compressed: bool,
+    length: u16,
+    data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+    status: u8,
+    /// Status inquiries left before current print is finished
+    busy_polls: u8,
+    /// Tile data received with DATA packets
+    buffer: Vec<u8>,
+    /// Pixels of the page printed so far. Multiple prints are joined
+    /// into a single page until paper is fed after one
+    page: Vec<u8>,
+    page_sender: mpsc::Sender<PrintedPage>,
+}
+
+impl Printer {
+    pub fn new() -> (Self, mpsc::Receiver<PrintedPage>) {
+        let (page_sender, page_receiver) = mpsc::channel();
+        let printer = Self {
+            state: PacketState::MagicByte1,
+            command: 0,
+            compressed: false,
+            length: 0,
+            data: vec![],
+            checksum: 0,
+            received_checksum: 0,
+            status: 0,
+            busy_polls: 0,
+            buffer: vec![],
+            page: vec![],
+            page_sender,
+        };
+        (printer, page_receiver)
+    }
+
+    /// Handles a received byte and returns the byte sent back
+    fn receive(&mut self, byte: u8) -> u8 {
+        let mut response = 0x00;
+        match self.state {
+            PacketState::MagicByte1 => {
+                if byte == 0x88 {
+                    self.state = PacketState::MagicByte2;
+                }
+            }
+            PacketState::MagicByte2 => {
+                self.state = if byte == 0x33 {
+                    PacketState::Command
+                } else {
+                    PacketState::MagicByte1
+                };
+            }
+            PacketState::Command => {
+                self.command = byte;
+                self.checksum = byte as u16;
+                self.state = PacketState::Compression;
+            }
+            PacketState::Compression => {
+                self.compressed = byte & 0b1 > 0;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                self.state = PacketState::LengthLow;
+            }
+            PacketState::LengthLow => {
+                self.length = byte as u16;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                self.state = PacketState::LengthHigh;
+            }
+            PacketState::LengthHigh => {
+                self.length |= (byte as u16) << 8;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                self.data.clear();
+                self.state = if self.length == 0 {
+                    PacketState::ChecksumLow
+                } else {
+                    PacketState::Data
+                };
+            }
+            PacketState::Data => {
+                self.data.push(byte);
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                if self.data.len() == self.length as usize {
+                    self.state = PacketState::ChecksumLow;
+                }
+            }
+            PacketState::ChecksumLow => {
+                self.received_checksum = byte as u16;
+                self.state = PacketState::ChecksumHigh;
+            }
+            PacketState::ChecksumHigh => {
+                self.received_checksum |= (byte as u16) << 8;
+                self.state = PacketState::KeepAlive;
+            }
+            PacketState::KeepAlive => {
+                response = 0x81;
+                self.state = PacketState::Status;
+            }
+            PacketState::Status => {
+                if self.received_checksum == self.checksum {
+                    self.status &= !status::CHECKSUM_ERROR;
+                    self.run_command();
+                } else {
+                    self.status |= status::CHECKSUM_ERROR;
+                }
+                response = self.status;
+                self.state = PacketState::MagicByte1;
+            }
+        }
+        response
+    }
+
+    fn run_command(&mut self) {
+        match self.command {
+            command::INIT => {
+                self.buffer.clear();
+                self.status = 0;
+                self.busy_polls = 0;
+            }
+            command::DATA => {
+                let data = std::mem::take(&mut self.data);
+                if self.compressed {
+                    self.decompress(&data);
+                } else {
+                    self.buffer.extend_from_slice(&data);
+                }
+                self.buffer.truncate(BUFFER_SIZE);
+                if !self.buffer.is_empty() {
+                    self.status |= status::UNPROCESSED_DATA;
+                }
+                if self.buffer.len() == BUFFER_SIZE {
+                    self.status |= status::IMAGE_DATA_FULL;
+                }
+            }
+            command::PRINT => {
+                if self.data.len() < 4 {
+                    return;
+                }
+                let margins = self.data[1];
+                let palette = self.data[2];
+                self.print(palette);
+                // Paper is fed after printing if there's a margin after the image,
+                // which means that the page is finished
+                if margins & 0x0F > 0 {
+                    self.finish_page();
+                }
+                self.status &= !(status::UNPROCESSED_DATA | status::IMAGE_DATA_FULL);
+                self.status |= status::PRINTING;
+                self.busy_polls = PRINT_BUSY_POLLS;
+            }
+            // Printing takes a while on real hardware,
+            // so report being busy for a few status checks
+            command::STATUS if self.busy_polls > 0 => {
+                self.busy_polls -= 1;
+                if self.busy_polls == 0 {
+                    self.status &= !status::PRINTING;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Decompresses run-length encoded data into the buffer
+    fn decompress(&mut self, data: &[u8]) {
+        let mut bytes = data.iter();
+        while let Some(&control) = bytes.next() {
+            if control & 0x80 > 0 {
+                // Next byte is repeated
+                let length = (control & 0x7F) as usize + 2;
+                if let Some(&byte) = bytes.next() {
+                    self.buffer.extend(std::iter::repeat_n(byte, length));
+                }
+            } else {
+                // Next bytes are copied as is
+                let length = control as usize + 1;
+                self.buffer.extend(bytes.by_ref().take(length));
+            }
+        }
+    }
+
+    /// Renders the tile data in the buffer onto the page
+    fn print(&mut self, palette: u8) {
+        // Palette of 0 is treated like the default palette
+        let palette = if palette == 0 { 0b1110_0100 } else { palette };
+        let tile_rows = self.buffer.len() / (16 * 20);
+        for y in 0..tile_rows * 8 {
+            for x in 0..PRINT_WIDTH {
+                let tile_index = (y / 8) * 20 + x / 8;
+                let byte_index = tile_index * 16 + (y % 8) * 2;
+                let bit = 0b1000_0000 >> (x % 8);
+                let low = self.buffer[byte_index] & bit > 0;
+                let high = self.buffer[byte_index + 1] & bit > 0;
+                let color_id = (low as u8) | ((high as u8) << 1);
+                self.page.push((palette >> (2 * color_id)) & 0b11);
+            }
+        }
+        self.buffer.clear();
+    }
+
+    /// Sends the finished page to the receiver
+    fn finish_page(&mut self) {
+        if self.page.is_empty() {
+            return;
+        }
+        let pixels = std::mem::take(&mut self.page);
+        let _ = self.page_sender.send(PrintedPage {
+            height: pixels.len() / PRINT_WIDTH,
+            pixels,
+        });
+    }
+}
+
+impl SerialDevice for Printer {
+    fn transfer(&mut self, byte: u8) -> u8 {
+        self.receive(byte)
+    }
+}
+
+impl Drop for Printer {
+    fn drop(&mut self) {
+        // Don't lose an unfinished page when the printer is disconnected
+        self.finish_page();
+    }
+}
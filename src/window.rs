@@ -1,5 +1,7 @@
-use super::cpu::{input::*, interrupts::*, registers::*};
 use super::*;
+use dmg_2025::cpu::{input::*, interrupts::*, ppu::Layer, registers::*};
+use dmg_2025::link::LinkConnection;
+use dmg_2025::printer::PrintedPage;
 use egui::{epaint::*, FontData, FontDefinitions, Style, TextureOptions, Visuals};
 use rodio::{
     buffer::SamplesBuffer,
@@ -19,12 +21,14 @@ mod clock;
 use clock::ExecutorInstruction;
 mod debug;
 mod input;
+mod link;
 mod menu;
 use menu::MenuPage;
+mod printer;
 mod saving;
 
 pub struct Window {
-    cpu: Arc<Mutex<Option<CPU>>>,
+    emulator: Arc<Mutex<Option<Emulator>>>,
     ctx: Arc<egui::Context>,
     paused: Arc<AtomicBool>,
     rom_loaded: bool,
@@ -34,6 +38,10 @@ pub struct Window {
     _stream: OutputStream,
     audio_queue: Arc<SourcesQueueInput<f32>>,
     input_state: Arc<Mutex<InputFlag>>,
+    rumble: Arc<AtomicBool>,
+    link_waiting: Arc<AtomicBool>,
+    link_connection: Arc<Mutex<Option<LinkConnection>>>,
+    printed_pages: Option<mpsc::Receiver<PrintedPage>>,
 
     options: Options,
     state_slot: u8,
@@ -45,6 +53,8 @@ pub struct Window {
     input_texture: TextureHandle,
     show_debug: bool,
     show_color_picker: bool,
+    /// Which layer's palette is edited in the color picker
+    color_picker_layer: Layer,
     show_profiler: bool,
 }
 
@@ -107,7 +117,7 @@ impl Window {
         puffin::set_scopes_on(false);
 
         Window {
-            cpu: Arc::new(Mutex::new(None)),
+            emulator: Arc::new(Mutex::new(None)),
             ctx: Arc::new(cc.egui_ctx.clone()),
             paused: Arc::new(AtomicBool::new(false)),
             rom_loaded: false,
@@ -116,7 +126,11 @@ impl Window {
             display_texture: Arc::new(Mutex::new(display_texture)),
             _stream: stream,
             audio_queue: queue,
-            input_state: Arc::new(Mutex::new(InputFlag::from_bits_truncate(0xFF))),
+            input_state: Arc::new(Mutex::new(InputFlag::all())),
+            rumble: Arc::new(AtomicBool::new(false)),
+            link_waiting: Arc::new(AtomicBool::new(false)),
+            link_connection: Arc::new(Mutex::new(None)),
+            printed_pages: None,
 
             options,
             state_slot: 1,
@@ -128,32 +142,53 @@ impl Window {
             input_texture,
             show_debug: false,
             show_color_picker: false,
+            color_picker_layer: Layer::BG,
             show_profiler: false,
         }
     }
 
-    fn load_rom_file(path: &str) -> Vec<u8> {
-        match std::fs::read(path) {
-            Ok(rom_file) => rom_file,
+    fn load_rom_file(path: &str) -> Result<Vec<u8>, String> {
+        std::fs::read(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "ROM file not found!".to_string(),
+            _ => e.to_string(),
+        })
+    }
+
+    /// Starts running the ROM in the options.
+    /// Returns false if it couldn't be loaded, in which case the previous ROM keeps running
+    fn init(&mut self) -> bool {
+        // Initialize emulator before stopping the old one
+        let emulator = Self::load_rom_file(&self.options.rom_path).and_then(|rom_file| {
+            Emulator::new(rom_file, self.options.emulator_options()).map_err(|e| e.to_string())
+        });
+        let mut emulator = match emulator {
+            Ok(emulator) => emulator,
             Err(e) => {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    eprintln!("ROM file not found!");
-                }
-                panic!("{e}")
+                eprintln!("Failed to load ROM: {e}");
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Failed to load ROM")
+                    .set_description(e)
+                    .show();
+                return false;
             }
-        }
-    }
+        };
 
-    fn init(&mut self) {
         // Stop executor if running
         if let Some(tx) = &self.clock_tx {
             let _ = tx.send(ExecutorInstruction::Stop);
         }
-        // Initialize CPU
-        *self.cpu.lock().unwrap() = Some(CPU::new(
-            Self::load_rom_file(&self.options.rom_path),
-            &self.options,
-        ));
+        emulator.connect_camera(self.options.camera_source());
+        let mut emulator_option = self.emulator.lock().unwrap();
+        // Keep link cable plugged in when switching ROMs
+        if let Some(device) = emulator_option
+            .as_mut()
+            .and_then(|old_emulator| old_emulator.disconnect_serial())
+        {
+            emulator.connect_serial(device);
+        }
+        *emulator_option = Some(emulator);
+        drop(emulator_option);
 
         // Load saved ram from file and initialize memory map
         self.load_ram();
@@ -168,12 +203,14 @@ impl Window {
 
         self.rom_loaded = true;
         self.paused.store(false, Ordering::Relaxed);
+        true
     }
 }
 
 impl eframe::App for Window {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         profiling::function_scope!();
+        self.save_printed_pages();
         // Render the main display window
         let central_frame = egui::Frame::central_panel(&ctx.style()).inner_margin(Margin::ZERO);
         egui::CentralPanel::default()
@@ -186,12 +223,14 @@ impl eframe::App for Window {
 
                 if self.rom_loaded {
                     profiling::scope!("Drawing");
-                    // Calculate how texture should be resized on the screen to keep aspect ratio
+                    // Calculate how texture should be resized on the screen to keep aspect ratio.
+                    // Texture is bigger than the display when it has the Super Game Boy border
+                    let texture_size = self.display_texture.lock().unwrap().size_vec2();
                     let screen_size = ui.available_size();
                     let mut rect =
                         Rect::from_min_max(pos2(0.0, 0.0), pos2(screen_size.x, screen_size.y));
-                    let x_diff = rect.width() / 160.0;
-                    let y_diff = rect.height() / 144.0;
+                    let x_diff = rect.width() / texture_size.x;
+                    let y_diff = rect.height() / texture_size.y;
                     let offset: Vec2 = if x_diff > y_diff {
                         rect.set_width(rect.width() / (x_diff / y_diff));
                         Vec2::new((screen_size.x - rect.width()) / 2.0, 0.0)
@@ -200,11 +239,19 @@ impl eframe::App for Window {
                         Vec2::new(0.0, (screen_size.y - rect.height()) / 2.0)
                     };
                     let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+                    // Shake the display by a pixel every other frame while the rumble motor is on
+                    let rumbling =
+                        self.rumble.load(Ordering::Relaxed) && !self.paused.load(Ordering::Relaxed);
+                    let shake = if rumbling && ctx.frame_nr() & 1 == 0 {
+                        Vec2::new(rect.width() / texture_size.x, 0.0)
+                    } else {
+                        Vec2::ZERO
+                    };
 
                     // Paint pixel texture
                     ui.painter().image(
                         self.display_texture.lock().unwrap().id(),
-                        rect.translate(offset),
+                        rect.translate(offset + shake),
                         uv,
                         Color32::WHITE,
                     );
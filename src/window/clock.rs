@@ -1,21 +1,26 @@
 use super::*;
 
+/// Time between VBlanks (59.7 hZ)
+const FRAME_DURATION: Duration = Duration::from_micros(16742);
+
 #[derive(PartialEq)]
 pub enum ExecutorInstruction {
     RunFrame,
     RunInstruction,
-    OptionsUpdated(Options),
+    OptionsUpdated(Box<Options>),
     Stop,
 }
 
 impl Window {
     pub fn start_executor(&mut self) -> mpsc::SyncSender<ExecutorInstruction> {
-        let cpu_ref = Arc::clone(&self.cpu);
+        let emulator_ref = Arc::clone(&self.emulator);
         let ctx = self.ctx.clone();
         let display_ref = Arc::clone(&self.display_texture);
         let paused_ref = Arc::clone(&self.paused);
         let input_ref = Arc::clone(&self.input_state);
+        let rumble_ref = Arc::clone(&self.rumble);
         let audio_queue_ref = Arc::clone(&self.audio_queue);
+        let link_ref = Arc::clone(&self.link_connection);
 
         let mut options = self.options.clone();
 
@@ -34,61 +39,62 @@ impl Window {
                 match instruction {
                     ExecutorInstruction::Stop => break,
                     ExecutorInstruction::OptionsUpdated(new_options) => {
-                        options = new_options;
+                        options = *new_options;
                         continue;
                     }
                     _ => {}
                 };
 
-                let mut cpu_option = cpu_ref.lock().unwrap();
-                let cpu = cpu_option.as_mut().unwrap();
+                // Keep in step with the link partner by skipping frames until it catches up.
+                // Waiting is done before locking the emulator, so the window stays responsive
+                if instruction == ExecutorInstruction::RunFrame {
+                    let link = link_ref.lock().unwrap().clone();
+                    if link.is_some_and(|link| !link.wait_for_peer(FRAME_DURATION)) {
+                        continue;
+                    }
+                }
+
+                let mut emulator_option = emulator_ref.lock().unwrap();
+                let emulator = emulator_option.as_mut().unwrap();
 
                 // Update input
                 let input = input_ref.lock().unwrap();
-                cpu.update_input(&input);
+                emulator.set_input(input.complement());
                 drop(input);
 
                 // Run emulation until next VBlank
                 if instruction == ExecutorInstruction::RunFrame {
                     profiling::scope!("CPU Frame");
-                    loop {
-                        // Break loop if execution function returns true (meaning VBlank was hit)
-                        if cpu.execute() {
-                            // If profiling was enabled, disable it after frame and pause profiler
-                            // data so frame data can be viewed
-                            if cpu.profiling {
-                                cpu.profiling = false;
-                                puffin::set_scopes_on(false);
-                            }
-                            // Update display texture
-                            let image = Self::get_display_texture(cpu, &options);
-                            display_ref
-                                .lock()
-                                .unwrap()
-                                .set(image, TextureOptions::NEAREST);
-                            // Append currently sampled audio buffer to playback queue
-                            audio_queue_ref.append(
-                                SamplesBuffer::new(
-                                    2,
-                                    options.audio_sample_rate,
-                                    cpu.apu.receive_buffer(),
-                                )
-                                .amplify((options.volume as f32) / 100.0),
-                            );
-                            drop(cpu_option);
-                            // Request repaint to refresh display
-                            if !paused_ref.load(Ordering::Relaxed) {
-                                ctx.request_repaint();
-                            }
-                            break;
-                        }
+                    emulator.run_frame();
+                    // If profiling was enabled, disable it after frame and pause profiler
+                    // data so frame data can be viewed
+                    if emulator.cpu.profiling {
+                        emulator.cpu.profiling = false;
+                        puffin::set_scopes_on(false);
+                    }
+                    // Update display texture
+                    let image = Self::get_display_texture(emulator, &options);
+                    display_ref
+                        .lock()
+                        .unwrap()
+                        .set(image, TextureOptions::NEAREST);
+                    rumble_ref.store(emulator.take_rumble(), Ordering::Relaxed);
+                    // Append currently sampled audio buffer to playback queue
+                    audio_queue_ref.append(
+                        SamplesBuffer::new(2, options.audio_sample_rate, emulator.take_audio())
+                            .amplify((options.volume as f32) / 100.0),
+                    );
+                    drop(emulator_option);
+                    // Request repaint to refresh display
+                    if !paused_ref.load(Ordering::Relaxed) {
+                        ctx.request_repaint();
                     }
                 }
                 // Otherwise only execute one instruction manually
                 else {
-                    cpu.execute();
+                    emulator.step_instruction();
                     // Clear APU buffer
-                    cpu.apu.receive_buffer();
+                    emulator.take_audio();
                     ctx.request_repaint();
                 }
             }
@@ -96,19 +102,38 @@ impl Window {
         tx
     }
 
-    pub fn get_display_texture(cpu: &CPU, options: &Options) -> ColorImage {
-        let palette = match options.palette_preset {
-            0 => Palette::original(),
-            1 => Palette::lcd(),
-            2 => options.custom_palette.clone(),
-            _ => unreachable!(),
-        };
+    pub fn get_display_texture(emulator: &Emulator, options: &Options) -> ColorImage {
+        // Super Game Boy screen is bigger because of the border
+        if let Some(screen) = emulator.sgb_screen() {
+            let (width, height) = (screen.len(), screen[0].len());
+            let mut pixels = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for column in screen.iter() {
+                    let [r, g, b] = rgb555_to_rgb(column[y]);
+                    pixels.push(Color32::from_rgb(r, g, b));
+                }
+            }
+            return ColorImage {
+                size: [width, height],
+                pixels,
+            };
+        }
+        let display = emulator.framebuffer();
+        let layers = emulator.layers();
+        let colored = emulator.has_color();
+        let palettes = options.palettes();
         let mut pixels = vec![];
         for y in 0..144 {
-            for x in 0..160 {
-                // Loop through front display
-                let pixel = cpu.ppu.display[x][y];
-                let color = palette.get_col(pixel);
+            // Loop through front display
+            for (column, layer_column) in display.iter().zip(layers) {
+                // CGB games and colorized games have their own colors,
+                // so the palettes are only used for plain DMG games
+                let color = if colored {
+                    let [r, g, b] = rgb555_to_rgb(column[y]);
+                    Color32::from_rgb(r, g, b)
+                } else {
+                    palettes.get(layer_column[y]).get_col(column[y] as u8)
+                };
                 pixels.push(color);
             }
         }
@@ -132,8 +157,7 @@ impl Window {
             if res.is_err() {
                 break;
             }
-            // Wait for the duration between VBlanks (59.7 hZ)
-            thread::sleep(Duration::from_micros(16742));
+            thread::sleep(FRAME_DURATION);
         });
     }
 }
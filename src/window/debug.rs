@@ -12,11 +12,11 @@ impl Window {
 
     /// Renders a debug window with displays for the current state of the CPU
     pub fn render_debug(&mut self, _ctx: &Context, ui: &mut Ui) {
-        let cpu = self.cpu.lock().unwrap();
-        if cpu.is_none() {
+        let mut emulator_option = self.emulator.lock().unwrap();
+        let Some(emulator) = emulator_option.as_mut() else {
             return;
-        }
-        let cpu = cpu.as_ref().unwrap();
+        };
+        let cpu = &emulator.cpu;
         Grid::new("debug_grid").min_col_width(200.0).show(ui, |ui| {
             ui.vertical(|ui| {
                 ui.monospace(format!(
@@ -81,19 +81,29 @@ impl Window {
                     cpu.timer.tima,
                 ));
                 ui.monospace(format!("PPU {} {:0>10b}", cpu.ppu.mode, cpu.ppu.control));
+                ui.monospace(format!(
+                    "OAM DMA {} {:#06X} {}/160",
+                    Self::bool_to_emoji(cpu.ppu.oam_dma.active),
+                    u16::from_be_bytes([cpu.ppu.oam_dma.source, cpu.ppu.oam_dma.index]),
+                    cpu.ppu.oam_dma.index,
+                ));
             });
 
             ui.vertical(|ui| {
                 ui.monospace(format!(
                     "Next: {:04X} {:04X} {:04X} {:04X}",
-                    cpu.read(cpu.reg.pc),
-                    cpu.read(cpu.reg.pc.wrapping_add(1)),
-                    cpu.read(cpu.reg.pc.wrapping_add(2)),
-                    cpu.read(cpu.reg.pc.wrapping_add(3))
+                    cpu.read_unrestricted(cpu.reg.pc),
+                    cpu.read_unrestricted(cpu.reg.pc.wrapping_add(1)),
+                    cpu.read_unrestricted(cpu.reg.pc.wrapping_add(2)),
+                    cpu.read_unrestricted(cpu.reg.pc.wrapping_add(3))
                 ));
             });
 
             ui.end_row();
         });
+        ui.checkbox(
+            &mut emulator.cpu.access_restrictions,
+            "Restrict VRAM and OAM access",
+        );
     }
 }
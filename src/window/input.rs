@@ -71,9 +71,9 @@ impl Window {
                         }
                         // Run CPU profiling for one frame
                         Key::F5 => {
-                            let mut cpu_option = self.cpu.lock().unwrap();
-                            if cpu_option.is_some() {
-                                cpu_option.as_mut().unwrap().profiling = true;
+                            let mut emulator_option = self.emulator.lock().unwrap();
+                            if let Some(emulator) = emulator_option.as_mut() {
+                                emulator.cpu.profiling = true;
                             }
                         }
                         Key::F7 => {
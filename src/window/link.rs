@@ -0,0 +1,82 @@
+use super::*;
+use dmg_2025::link::{LinkCable, LinkHost, DEFAULT_LINK_PORT};
+
+impl Window {
+    /// Returns if a link cable session is currently active
+    pub fn link_connected(&self) -> bool {
+        self.link_connection
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|connection| connection.is_connected())
+    }
+
+    /// Plugs link cable into the running emulator
+    fn plug_link_cable(
+        emulator_ref: &Mutex<Option<Emulator>>,
+        connection_ref: &Mutex<Option<LinkConnection>>,
+        cable: LinkCable,
+    ) {
+        let mut emulator_option = emulator_ref.lock().unwrap();
+        if let Some(emulator) = emulator_option.as_mut() {
+            *connection_ref.lock().unwrap() = Some(cable.connection());
+            emulator.connect_serial(Box::new(cable));
+            println!("Link cable connected");
+        }
+    }
+
+    /// Starts waiting for another instance to join the link session on a separate thread
+    pub fn host_link(&mut self) {
+        self.disconnect_printer();
+        let host = match LinkHost::bind(DEFAULT_LINK_PORT) {
+            Ok(host) => host,
+            Err(e) => {
+                eprintln!("Failed to host link session: {e}");
+                return;
+            }
+        };
+        println!("Waiting for link partner on port {DEFAULT_LINK_PORT}");
+        self.link_waiting.store(true, Ordering::Relaxed);
+
+        let emulator_ref = Arc::clone(&self.emulator);
+        let connection_ref = Arc::clone(&self.link_connection);
+        let waiting_ref = Arc::clone(&self.link_waiting);
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            // Waiting is stopped by clearing the flag
+            while waiting_ref.load(Ordering::Relaxed) {
+                match host.try_accept() {
+                    Ok(Some(cable)) => {
+                        Self::plug_link_cable(&emulator_ref, &connection_ref, cable);
+                        break;
+                    }
+                    Ok(None) => thread::sleep(Duration::from_millis(50)),
+                    Err(e) => {
+                        eprintln!("Failed to accept link partner: {e}");
+                        break;
+                    }
+                }
+            }
+            waiting_ref.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Joins a link session hosted by another instance
+    pub fn join_link(&mut self) {
+        self.disconnect_printer();
+        match LinkCable::join(DEFAULT_LINK_PORT) {
+            Ok(cable) => Self::plug_link_cable(&self.emulator, &self.link_connection, cable),
+            Err(e) => eprintln!("Failed to join link session: {e}"),
+        }
+    }
+
+    /// Stops waiting for a link partner, or unplugs the link cable
+    pub fn disconnect_link(&mut self) {
+        self.link_waiting.store(false, Ordering::Relaxed);
+        if let Some(emulator) = self.emulator.lock().unwrap().as_mut() {
+            emulator.disconnect_serial();
+        }
+        *self.link_connection.lock().unwrap() = None;
+    }
+}
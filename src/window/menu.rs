@@ -1,7 +1,7 @@
 use super::*;
 use egui::{load::SizedTexture, Context, Image, ImageSource, RichText, Ui};
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(PartialEq)]
 pub enum MenuPage {
@@ -86,9 +86,17 @@ impl Window {
                             columns[0].vertical_centered(|ui| {
                                 if ui.button("Load ROM  ").clicked() {
                                     if let Some(rom_path) = self.open_rom_dialog() {
-                                        self.options.rom_path = rom_path.to_str().unwrap().into();
-                                        self.options.save();
-                                        self.init();
+                                        let previous_path = std::mem::replace(
+                                            &mut self.options.rom_path,
+                                            rom_path.to_str().unwrap().into(),
+                                        );
+                                        // Saves are named after the ROM path, so keep the old one
+                                        // if the previous ROM is still running
+                                        if self.init() {
+                                            self.options.save();
+                                        } else {
+                                            self.options.rom_path = previous_path;
+                                        }
                                     }
                                 }
                                 ui.add_space(scale * 12.0);
@@ -143,6 +151,56 @@ impl Window {
                                 });
                             });
                         });
+
+                        // Link cable session
+                        ui.add_space(scale * 12.0);
+                        let link_waiting = self.link_waiting.load(Ordering::Relaxed);
+                        let link_connected = self.link_connected();
+                        ui.columns(2, |columns| {
+                            columns[0].vertical_centered(|ui| {
+                                if link_waiting || link_connected {
+                                    let text = if link_connected { "Unlink" } else { "Cancel" };
+                                    if ui.button(text).clicked() {
+                                        self.disconnect_link();
+                                    }
+                                } else if ui
+                                    .add_enabled(self.rom_loaded, egui::Button::new("Host link"))
+                                    .clicked()
+                                {
+                                    self.host_link();
+                                }
+                            });
+                            columns[1].vertical_centered(|ui| {
+                                if link_waiting || link_connected {
+                                    let text = if link_connected {
+                                        "Linked"
+                                    } else {
+                                        "Waiting..."
+                                    };
+                                    ui.label(RichText::new(text).color(Color32::from_gray(200)));
+                                } else if ui
+                                    .add_enabled(self.rom_loaded, egui::Button::new("Join link"))
+                                    .clicked()
+                                {
+                                    self.join_link();
+                                }
+                            });
+                        });
+
+                        // Game Boy Printer
+                        ui.add_space(scale * 4.0);
+                        ui.vertical_centered(|ui| {
+                            if self.printer_connected() {
+                                if ui.button("Remove printer").clicked() {
+                                    self.disconnect_printer();
+                                }
+                            } else if ui
+                                .add_enabled(self.rom_loaded, egui::Button::new("Connect printer"))
+                                .clicked()
+                            {
+                                self.connect_printer();
+                            }
+                        });
                     }
                     // Input rebinding page
                     MenuPage::Input => {
@@ -159,6 +217,18 @@ impl Window {
                         self.add_input_rebind(ui, pos2(117.0, 48.0), 30.0, InputFlag::A);
                         self.add_input_rebind(ui, pos2(11.0, 89.0), 60.0, InputFlag::SELECT);
                         self.add_input_rebind(ui, pos2(74.0, 89.0), 60.0, InputFlag::START);
+                        // Tilt is only used by cartridges with an accelerometer
+                        ui.put(
+                            Rect::from_min_size(
+                                pos2(scale * 60.0, scale * 110.0),
+                                vec2(scale * 28.0, scale * 8.0),
+                            ),
+                            egui::Label::new("Tilt"),
+                        );
+                        self.add_input_rebind(ui, pos2(89.0, 110.0), 30.0, InputFlag::TILT_LEFT);
+                        self.add_input_rebind(ui, pos2(121.0, 110.0), 30.0, InputFlag::TILT_RIGHT);
+                        self.add_input_rebind(ui, pos2(105.0, 100.0), 30.0, InputFlag::TILT_UP);
+                        self.add_input_rebind(ui, pos2(105.0, 120.0), 30.0, InputFlag::TILT_DOWN);
 
                         ui.set_style(reset_style_arc.clone());
                         if ui
@@ -183,7 +253,11 @@ impl Window {
                                 ui.label(
                                     RichText::new("Window scale").color(Color32::from_gray(200)),
                                 );
+                                ui.label(RichText::new("Boot ROM").color(Color32::from_gray(200)));
+                                ui.label(RichText::new("Camera").color(Color32::from_gray(200)));
                                 ui.label(RichText::new("Palette").color(Color32::from_gray(200)));
+                                ui.label(RichText::new("Colorize").color(Color32::from_gray(200)));
+                                ui.label(RichText::new("Super GB").color(Color32::from_gray(200)));
                             });
                             columns[1].vertical_centered_justified(|ui| {
                                 // Volume
@@ -234,6 +308,66 @@ impl Window {
                                     };
                                 });
 
+                                // Boot ROM, applied when next ROM is loaded
+                                ui.horizontal(|ui| {
+                                    if self.options.boot_rom_path.is_empty() {
+                                        if ui
+                                            .button("Built-in")
+                                            .on_hover_text("Click to choose a boot ROM file")
+                                            .clicked()
+                                        {
+                                            if let Some(path) = self.open_boot_rom_dialog() {
+                                                self.options.boot_rom_path =
+                                                    path.to_str().unwrap().into();
+                                                self.options.save();
+                                            }
+                                        }
+                                    } else if ui
+                                        .button("From file")
+                                        .on_hover_text(format!(
+                                            "{}\nClick to use the built-in boot ROM",
+                                            self.options.boot_rom_path
+                                        ))
+                                        .clicked()
+                                    {
+                                        self.options.boot_rom_path.clear();
+                                        self.options.save();
+                                    }
+                                });
+
+                                // Image source of the Pocket Camera
+                                ui.horizontal(|ui| {
+                                    let response = if self.options.camera_path.is_empty() {
+                                        ui.button("Test pattern").on_hover_text(
+                                            "Click to choose an image, right click to choose a folder of frames",
+                                        )
+                                    } else {
+                                        let source = if Path::new(&self.options.camera_path).is_dir() {
+                                            "From folder"
+                                        } else {
+                                            "From file"
+                                        };
+                                        ui.button(source).on_hover_text(format!(
+                                            "{}\nClick to use the test pattern",
+                                            self.options.camera_path
+                                        ))
+                                    };
+                                    let path = if !self.options.camera_path.is_empty() {
+                                        response.clicked().then(PathBuf::new)
+                                    } else if response.clicked() {
+                                        self.open_camera_dialog(false)
+                                    } else if response.secondary_clicked() {
+                                        self.open_camera_dialog(true)
+                                    } else {
+                                        None
+                                    };
+                                    if let Some(path) = path {
+                                        self.options.camera_path = path.to_str().unwrap().into();
+                                        self.options.save();
+                                        self.update_camera_source();
+                                    }
+                                });
+
                                 // Palette
                                 ui.horizontal(|ui| {
                                     let palette_str = match self.options.palette_preset {
@@ -269,10 +403,42 @@ impl Window {
                                     }
                                 });
 
+                                // Colorization of DMG games
+                                ui.horizontal(|ui| {
+                                    if self.add_arrow(ui, false).clicked() {
+                                        self.options.cycle_colorization(false);
+                                        self.update_colorization();
+                                    }
+                                    ui.add_sized(
+                                        [scale * 50.0, scale * 8.0],
+                                        egui::Label::new(
+                                            RichText::new(self.options.colorization_name())
+                                                .color(Color32::from_gray(200)),
+                                        ),
+                                    );
+                                    if self.add_arrow(ui, true).clicked() {
+                                        self.options.cycle_colorization(true);
+                                        self.update_colorization();
+                                    }
+                                });
+
+                                // Super Game Boy, applied when next ROM is loaded
+                                ui.horizontal(|ui| {
+                                    let text = if self.options.sgb { "On" } else { "Off" };
+                                    if ui
+                                        .button(text)
+                                        .on_hover_text("Applied when the next ROM is loaded")
+                                        .clicked()
+                                    {
+                                        self.options.sgb = !self.options.sgb;
+                                        self.options.save();
+                                    }
+                                });
+
                                 // Custom palette
                                 ui.horizontal(|ui| {
                                     if self.options.palette_preset == 2
-                                        && ui.button("Edit palette").clicked()
+                                        && ui.button("Edit palettes").clicked()
                                     {
                                         self.show_color_picker = true;
                                     }
@@ -300,6 +466,8 @@ impl Window {
                             self.options.save();
 
                             self.update_cpu_options();
+                            self.update_camera_source();
+                            self.update_colorization();
                             self.update_display();
                             self.update_window();
                         }
@@ -359,7 +527,7 @@ impl Window {
                 .with_maximize_button(false)
                 .with_minimize_button(false)
                 .with_title("Edit palette")
-                .with_inner_size([600.0, 400.0]),
+                .with_inner_size([600.0, 440.0]),
             |ctx, class| {
                 assert!(
                     class == egui::ViewportClass::Immediate,
@@ -367,6 +535,17 @@ impl Window {
                 );
 
                 egui::CentralPanel::default().show(ctx, |ui| {
+                    // Each layer has its own palette
+                    ui.horizontal(|ui| {
+                        for (layer, name) in [
+                            (Layer::BG, "Background"),
+                            (Layer::OBJ0, "Objects 0"),
+                            (Layer::OBJ1, "Objects 1"),
+                        ] {
+                            ui.selectable_value(&mut self.color_picker_layer, layer, name);
+                        }
+                    });
+                    ui.separator();
                     ui.columns(2, |columns| {
                         columns[0].vertical_centered_justified(|ui| {
                             self.color_picker(ui, 0);
@@ -393,7 +572,11 @@ impl Window {
             ui.label(RichText::new(format!("Color {index}")).color(Color32::WHITE));
             ui.add_space(16.0);
             if ui.button("Reset").clicked() {
-                *self.options.custom_palette.get_mut(index) = Palette::original().get_col(index);
+                *self
+                    .options
+                    .custom_palettes
+                    .get_mut(self.color_picker_layer)
+                    .get_mut(index) = Palette::original().get_col(index);
                 self.update_cpu_options();
                 if self.paused.load(Ordering::Relaxed) {
                     self.update_display();
@@ -402,7 +585,10 @@ impl Window {
         });
         if egui::widgets::color_picker::color_picker_color32(
             ui,
-            self.options.custom_palette.get_mut(index),
+            self.options
+                .custom_palettes
+                .get_mut(self.color_picker_layer)
+                .get_mut(index),
             egui::color_picker::Alpha::Opaque,
         ) {
             self.update_cpu_options();
@@ -448,6 +634,23 @@ impl Window {
             .pick_file()
     }
 
+    fn open_boot_rom_dialog(&self) -> Option<PathBuf> {
+        rfd::FileDialog::new()
+            .set_title("Choose boot ROM file")
+            .add_filter("Boot ROM", &["bin"])
+            .add_filter("All files", &["*"])
+            .pick_file()
+    }
+
+    fn open_camera_dialog(&self, folder: bool) -> Option<PathBuf> {
+        let dialog = rfd::FileDialog::new().set_title("Choose camera image");
+        if folder {
+            dialog.pick_folder()
+        } else {
+            dialog.add_filter("Image", &["png"]).pick_file()
+        }
+    }
+
     fn add_arrow(&self, ui: &mut Ui, right: bool) -> egui::Response {
         let scale = self.options.window_scale as f32;
         let angle = if right { std::f32::consts::PI } else { 0.0 };
@@ -488,9 +691,9 @@ impl Window {
     }
 
     fn update_display(&mut self) {
-        let cpu_option = self.cpu.lock().unwrap();
-        if let Some(cpu) = cpu_option.as_ref() {
-            let image = Self::get_display_texture(cpu, &self.options);
+        let emulator_option = self.emulator.lock().unwrap();
+        if let Some(emulator) = emulator_option.as_ref() {
+            let image = Self::get_display_texture(emulator, &self.options);
             self.display_texture
                 .lock()
                 .unwrap()
@@ -498,9 +701,29 @@ impl Window {
         }
     }
 
+    fn update_camera_source(&mut self) {
+        let mut emulator_option = self.emulator.lock().unwrap();
+        if let Some(emulator) = emulator_option.as_mut() {
+            emulator.connect_camera(self.options.camera_source());
+        }
+    }
+
+    fn update_colorization(&mut self) {
+        self.options.save();
+        let mut emulator_option = self.emulator.lock().unwrap();
+        if let Some(emulator) = emulator_option.as_mut() {
+            emulator.set_colorization(self.options.colorization);
+        }
+        drop(emulator_option);
+        self.update_cpu_options();
+        self.update_display();
+    }
+
     fn update_cpu_options(&mut self) {
         if let Some(tx) = &self.clock_tx {
-            let _ = tx.send(ExecutorInstruction::OptionsUpdated(self.options.clone()));
+            let _ = tx.send(ExecutorInstruction::OptionsUpdated(Box::new(
+                self.options.clone(),
+            )));
         }
     }
 }
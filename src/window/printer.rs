@@ -0,0 +1,76 @@
+use super::*;
+use dmg_2025::printer::{Printer, PRINT_WIDTH};
+use std::path::PathBuf;
+use std::sync::mpsc::TryRecvError;
+
+impl Window {
+    /// Returns if the Game Boy Printer is plugged into the link port
+    pub fn printer_connected(&self) -> bool {
+        self.printed_pages.is_some()
+    }
+
+    /// Plugs the Game Boy Printer into the link port in place of a link cable
+    pub fn connect_printer(&mut self) {
+        self.disconnect_link();
+        let (printer, printed_pages) = Printer::new();
+        if let Some(emulator) = self.emulator.lock().unwrap().as_mut() {
+            emulator.connect_serial(Box::new(printer));
+            self.printed_pages = Some(printed_pages);
+            println!("Printer connected");
+        }
+    }
+
+    /// Unplugs the printer, saving the page that was being printed
+    pub fn disconnect_printer(&mut self) {
+        if !self.printer_connected() {
+            return;
+        }
+        if let Some(emulator) = self.emulator.lock().unwrap().as_mut() {
+            emulator.disconnect_serial();
+        }
+        self.save_printed_pages();
+        self.printed_pages = None;
+    }
+
+    /// Saves pages printed since last call as PNGs into the save folder
+    pub fn save_printed_pages(&mut self) {
+        let Some(printed_pages) = &self.printed_pages else {
+            return;
+        };
+        let mut pages = vec![];
+        loop {
+            match printed_pages.try_recv() {
+                Ok(page) => pages.push(page),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.printed_pages = None;
+                    break;
+                }
+            }
+        }
+
+        // Printer doesn't know about layers, so prints use the background palette
+        let palette = self.options.palettes().bg;
+        for page in pages {
+            let image =
+                ::image::RgbImage::from_fn(PRINT_WIDTH as u32, page.height as u32, |x, y| {
+                    let shade = page.pixels[y as usize * PRINT_WIDTH + x as usize];
+                    ::image::Rgb(palette.get_col(shade).to_array()[..3].try_into().unwrap())
+                });
+            let path = self.get_print_path();
+            match image.save(&path) {
+                Ok(_) => println!("Saved print to {}", path.to_str().unwrap()),
+                Err(e) => eprintln!("Failed to save print: {e}"),
+            }
+        }
+    }
+
+    /// Returns the first unused path for a printed page
+    fn get_print_path(&self) -> PathBuf {
+        let folder = self.get_save_folder();
+        (1..)
+            .map(|index| folder.join(format!("print{index}.png")))
+            .find(|path| !path.exists())
+            .unwrap()
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use cpu::memory::CartridgeInfo;
+use dmg_2025::cpu::memory::CartridgeInfo;
 use memmap2::MmapMut;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
@@ -31,11 +31,20 @@ impl Window {
     fn get_mmap(&self, info: CartridgeInfo) -> MmapMut {
         let file_path = self.get_save_folder().join("save.bin");
         let file = if file_path.exists() {
-            OpenOptions::new()
+            let file = OpenOptions::new()
                 .read(true)
                 .write(true)
                 .open(file_path)
-                .expect("Couldn't open save file")
+                .expect("Couldn't open save file");
+            // Make room for RTC state in saves that don't have it yet
+            let save_size = info.save_size() as u64;
+            if file
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() < save_size)
+            {
+                let _ = file.set_len(save_size);
+            }
+            file
         } else {
             let mut new_file = OpenOptions::new()
                 .read(true)
@@ -43,15 +52,15 @@ impl Window {
                 .create_new(true)
                 .open(file_path)
                 .expect("Couldn't create new save file");
-            let _ = new_file.write_all(vec![0; usize::from(0x2000 * info.ram_banks)].as_slice());
+            let _ = new_file.write_all(vec![0; info.save_size()].as_slice());
             new_file
         };
         unsafe { MmapMut::map_mut(&file).expect("Couldn't initialize memory map") }
     }
 
     pub fn load_ram(&mut self) {
-        let mut cpu_option = self.cpu.lock().unwrap();
-        let cpu = cpu_option.as_mut().unwrap();
+        let mut emulator_option = self.emulator.lock().unwrap();
+        let cpu = &mut emulator_option.as_mut().unwrap().cpu;
         // RAM is only saved in cartridges with battery
         if !cpu.mem.info.has_battery {
             return;
@@ -66,10 +75,10 @@ impl Window {
         if !self.rom_loaded {
             return;
         }
-        let cpu_option = self.cpu.lock().unwrap();
+        let emulator_option = self.emulator.lock().unwrap();
         let path = self.get_state_path();
         println!("Saving CPU state to {}", path.to_str().unwrap());
-        let state = serde_json::to_string::<CPU>(cpu_option.as_ref().unwrap())
+        let state = serde_json::to_string::<CPU>(&emulator_option.as_ref().unwrap().cpu)
             .expect("Failed to save CPU state: Serialization failed");
 
         if !self.get_save_folder().exists() {
@@ -100,24 +109,29 @@ impl Window {
                 .as_ref()
                 .unwrap()
                 .send(ExecutorInstruction::Stop);
-            let mut cpu_option = self.cpu.lock().unwrap();
+            let mut emulator_option = self.emulator.lock().unwrap();
+            let emulator = emulator_option.as_mut().unwrap();
 
             // Initialize new CPU from deserialized state using current ROM file
-            let rom = cpu_option.as_ref().unwrap().mem.mbc.rom.clone();
+            let rom = emulator.cpu.mem.mbc.rom.clone();
             let cpu_res = serde_json::from_str::<CPU>(&save);
             if let Ok(mut loaded_cpu) = cpu_res {
                 loaded_cpu.mem.mbc.load_rom(rom);
+                loaded_cpu.mem.boot_rom = std::mem::take(&mut emulator.cpu.mem.boot_rom);
                 if loaded_cpu.mem.info.has_battery {
                     loaded_cpu
                         .mem
                         .mbc
                         .load_memory_map(self.get_mmap(loaded_cpu.mem.info), true);
                 }
-                *cpu_option = Some(loaded_cpu);
+                // Serial device and camera source aren't part of the saved state
+                loaded_cpu.serial.device = emulator.cpu.serial.device.take();
+                loaded_cpu.mem.mbc.camera.source = emulator.cpu.mem.mbc.camera.source.take();
+                emulator.cpu = loaded_cpu;
             } else {
                 eprintln!("Failed to load CPU state: Deserialization failed")
             }
-            drop(cpu_option);
+            drop(emulator_option);
 
             // Start executing
             self.paused.store(false, Ordering::Relaxed);
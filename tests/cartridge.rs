@@ -0,0 +1,107 @@
+//! Detecting the cartridge type from the ROM
+
+use dmg_2025::cpu::memory::{CartridgeInfo, MBCType};
+use dmg_2025::cpu::CPU;
+use dmg_2025::{Emulator, EmulatorOptions, LoadError};
+
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Writes a header with given cartridge type to the ROM bank starting at given address
+fn write_header(rom: &mut [u8], bank: usize, cartridge_type: u8, valid: bool) {
+    let header = &mut rom[bank + 0x0100..=bank + 0x014F];
+    if valid {
+        header[0x04..0x34].copy_from_slice(&NINTENDO_LOGO);
+    }
+    header[0x34..0x3A].copy_from_slice(b"HEADER");
+    header[0x47] = cartridge_type;
+    header[0x48] = 0x01;
+    let checksum = header[0x34..=0x4C]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+    header[0x4D] = if valid { checksum } else { checksum ^ 0xFF };
+}
+
+/// Returns a 64 KiB MBC1 ROM that has an MMM01 type byte where the menu header would be
+fn rom_with_menu_header(valid: bool) -> Vec<u8> {
+    let mut rom = vec![0; 0x10000];
+    write_header(&mut rom, 0, 0x01, true);
+    write_header(&mut rom, 0x8000, 0x0B, valid);
+    rom
+}
+
+#[test]
+fn mmm01_is_detected_from_menu_header() {
+    let info = CartridgeInfo::from_rom(&rom_with_menu_header(true));
+    assert!(matches!(info.mbc, MBCType::MMM01));
+    assert_eq!(info.rom_banks, 4);
+}
+
+#[test]
+fn invalid_menu_header_is_ignored() {
+    let info = CartridgeInfo::from_rom(&rom_with_menu_header(false));
+    assert!(matches!(info.mbc, MBCType::MBC1));
+
+    // Only the logo is missing
+    let mut rom = rom_with_menu_header(true);
+    rom[0x8104] = 0x00;
+    let info = CartridgeInfo::from_rom(&rom);
+    assert!(matches!(info.mbc, MBCType::MBC1));
+}
+
+#[test]
+fn unsupported_cartridge_is_refused() {
+    let mut rom = vec![0; 0x8000];
+    rom[0x0147] = 0x42;
+    assert!(matches!(
+        Emulator::new(rom, EmulatorOptions::default()),
+        Err(LoadError::UnsupportedCartridge(0x42))
+    ));
+}
+
+#[test]
+fn unsupported_cartridge_runs_without_mbc() {
+    let mut rom = vec![0; 0x4000];
+    rom[0x0147] = 0x42;
+    rom[0x0150] = 0x12;
+    let mut cpu = CPU::new(rom, &EmulatorOptions::default());
+    assert_eq!(cpu.read(0x0150), 0x12);
+    // Past the end of the ROM, and no RAM
+    assert_eq!(cpu.read(0x4000), 0xFF);
+    cpu.write(0xA000, 0x34);
+    assert_eq!(cpu.read(0xA000), 0xFF);
+}
+
+/// Returns a 64 KiB MBC2 ROM with each bank filled with its own number
+fn mbc2_rom() -> Vec<u8> {
+    let mut rom: Vec<u8> = (0..0x10000).map(|i| (i / 0x4000) as u8).collect();
+    write_header(&mut rom, 0, 0x05, true);
+    rom
+}
+
+/// Checks that the whole switchable ROM area maps given bank
+fn assert_rom_bank(cpu: &mut CPU, rom: &[u8], bank: usize) {
+    for address in 0x4000..=0x7FFF {
+        let expected = rom[bank * 0x4000 + address as usize - 0x4000];
+        assert_eq!(cpu.read(address), expected, "at {address:#06X}");
+    }
+}
+
+#[test]
+fn mbc2_bank_numbers_wrap_around_rom_size() {
+    let rom = mbc2_rom();
+    let mut cpu = CPU::new(rom.clone(), &EmulatorOptions::default());
+    cpu.write(0x2100, 3);
+    assert_rom_bank(&mut cpu, &rom, 3);
+    // Bank 4 doesn't exist on a 4 bank ROM, and maps bank 0 after masking
+    cpu.write(0x2100, 4);
+    assert_rom_bank(&mut cpu, &rom, 0);
+    cpu.write(0x2100, 6);
+    assert_rom_bank(&mut cpu, &rom, 2);
+    // Only a 4-bit value of 0 is incremented to 1
+    cpu.write(0x2100, 0x10);
+    assert_rom_bank(&mut cpu, &rom, 1);
+}
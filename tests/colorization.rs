@@ -0,0 +1,92 @@
+//! Looking up the palettes the CGB boot ROM gives to DMG games
+
+use dmg_2025::cpu::colorization::*;
+
+/// Returns a ROM with the given title, published by Nintendo
+fn rom_with_title(title: &str) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    rom[0x0134..0x0134 + title.len()].copy_from_slice(title.as_bytes());
+    rom[0x014B] = 0x01;
+    rom
+}
+
+fn button_palettes(name: &str) -> CompatPalettes {
+    BUTTON_PALETTES
+        .iter()
+        .find(|(button, _)| *button == name)
+        .unwrap()
+        .1
+}
+
+#[test]
+fn checksum_sums_title() {
+    assert_eq!(title_checksum(&rom_with_title("POKEMON RED")), Some(0x14));
+    assert_eq!(title_checksum(&rom_with_title("TETRIS")), Some(0xDB));
+    assert_eq!(title_checksum(&rom_with_title("ZELDA")), Some(0x70));
+
+    let mut rom = rom_with_title("TETRIS");
+    rom[0x014B] = 0x00;
+    assert_eq!(title_checksum(&rom), None);
+    // Newer games store the licensee as two characters
+    rom[0x014B] = 0x33;
+    rom[0x0144..=0x0145].copy_from_slice(b"01");
+    assert_eq!(title_checksum(&rom), Some(0xDB));
+}
+
+#[test]
+fn known_games_get_their_palettes() {
+    let pokemon_red = lookup_palettes(&rom_with_title("POKEMON RED"));
+    assert_eq!(pokemon_red.bg, [0x7FFF, 0x421F, 0x1CF2, 0x0000]);
+    assert_eq!(pokemon_red.obj0, [0x7FFF, 0x1BEF, 0x0200, 0x0000]);
+    assert_eq!(pokemon_red.obj1, pokemon_red.bg);
+
+    let pokemon_green = lookup_palettes(&rom_with_title("POKEMON GREEN"));
+    assert_eq!(pokemon_green.bg, [0x7FFF, 0x1BEF, 0x6180, 0x0000]);
+
+    assert_eq!(
+        lookup_palettes(&rom_with_title("TETRIS")),
+        button_palettes("Down+A")
+    );
+
+    let zelda = lookup_palettes(&rom_with_title("ZELDA"));
+    assert_eq!(zelda.bg, [0x7FFF, 0x421F, 0x1CF2, 0x0000]);
+    assert_eq!(zelda.obj0, [0x7FFF, 0x03E0, 0x0206, 0x0120]);
+    assert_eq!(zelda.obj1, [0x7FFF, 0x7E8C, 0x7C00, 0x0000]);
+}
+
+#[test]
+fn shared_checksums_are_told_apart_by_4th_letter() {
+    // POKEMON BLUE and VEGAS STAKES both sum to $61
+    let pokemon_blue = lookup_palettes(&rom_with_title("POKEMON BLUE"));
+    assert_eq!(pokemon_blue.bg, [0x7FFF, 0x7E8C, 0x7C00, 0x0000]);
+    assert_eq!(pokemon_blue.obj0, [0x7FFF, 0x421F, 0x1CF2, 0x0000]);
+    assert_ne!(
+        pokemon_blue,
+        lookup_palettes(&rom_with_title("VEGAS STAKES"))
+    );
+
+    // Super Mario Land's object palettes start in the middle of a palette in the boot ROM
+    let mario_land = lookup_palettes(&rom_with_title("SUPER MARIOLAND"));
+    assert_eq!(mario_land.bg, [0x7ED6, 0x4BFF, 0x2175, 0x0000]);
+    assert_eq!(mario_land.obj0, [0x0000, 0x7FFF, 0x421F, 0x1CF2]);
+    assert_eq!(mario_land.obj1, mario_land.obj0);
+
+    // Same checksum as TETRIS ATTACK, but no entry has X as the 4th letter
+    assert_eq!(title_checksum(&rom_with_title("XXXXS")), Some(0xB3));
+    assert_eq!(
+        lookup_palettes(&rom_with_title("XXXXS")),
+        button_palettes("Right")
+    );
+}
+
+#[test]
+fn unknown_games_get_default_palettes() {
+    assert_eq!(
+        lookup_palettes(&rom_with_title("HELLO WORLD")),
+        button_palettes("Right")
+    );
+
+    let mut rom = rom_with_title("POKEMON RED");
+    rom[0x014B] = 0x00;
+    assert_eq!(lookup_palettes(&rom), button_palettes("Right"));
+}
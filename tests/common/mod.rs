@@ -0,0 +1,64 @@
+//! Helpers for tests that build a scene by writing to VRAM and the LCD registers directly,
+//! and check the pixels drawn on the screen.
+
+// Every test file compiles this module separately, and not all of them use every helper
+#![allow(dead_code)]
+
+use dmg_2025::{Emulator, EmulatorOptions};
+
+/// Returns an emulator running a ROM that just loops forever, so the test has full control
+/// over the PPU. The boot ROM is skipped, and the emulator waits at the start of VBlank
+/// with the LCD on
+pub fn scene_emulator() -> Emulator {
+    let mut rom = vec![0; 0x8000];
+    // JR -2
+    rom[0x0100..0x0102].copy_from_slice(&[0x18, 0xFE]);
+    let mut emulator = Emulator::new(rom, EmulatorOptions::default()).unwrap();
+    emulator.cpu.mem.boot_rom_mapped = false;
+    emulator.cpu.reg.pc = 0x0100;
+    emulator.cpu.write(0xFF47, 0b1110_0100);
+    emulator.cpu.write(0xFF48, 0b1110_0100);
+    emulator.cpu.write(0xFF49, 0b0001_1011);
+    // First frame after turning the LCD on is always blank
+    emulator.cpu.write(0xFF40, 0b1000_0000);
+    emulator.run_frame();
+    emulator
+}
+
+/// Writes a tile to the tile data at $8000
+pub fn write_tile(emulator: &mut Emulator, index: u8, data: [u8; 16]) {
+    for (i, byte) in data.into_iter().enumerate() {
+        emulator
+            .cpu
+            .write(0x8000 + index as u16 * 16 + i as u16, byte);
+    }
+}
+
+/// Returns a tile that is filled with the given color ID
+pub fn solid_tile(color: u8) -> [u8; 16] {
+    let low = if color & 0b01 > 0 { 0xFF } else { 0x00 };
+    let high = if color & 0b10 > 0 { 0xFF } else { 0x00 };
+    std::array::from_fn(|i| if i % 2 == 0 { low } else { high })
+}
+
+/// Fills a 32x32 tile map at given address with tiles returned by the function
+pub fn fill_tile_map(emulator: &mut Emulator, address: u16, tile: impl Fn(u16, u16) -> u8) {
+    for y in 0..32 {
+        for x in 0..32 {
+            emulator.cpu.write(address + y * 32 + x, tile(x, y));
+        }
+    }
+}
+
+/// Runs the emulator until the PPU starts drawing given line
+pub fn run_until_line(emulator: &mut Emulator, ly: u8) {
+    while emulator.cpu.ppu.ly != ly {
+        emulator.step_instruction();
+    }
+}
+
+/// Returns the shade of a pixel in the most recently drawn frame.
+/// Background, window and OBP0 palettes map every color ID to the shade with the same number
+pub fn pixel(emulator: &Emulator, x: u8, y: u8) -> u16 {
+    emulator.framebuffer()[x as usize][y as usize]
+}
@@ -0,0 +1,89 @@
+//! LY and the STAT register, and when the STAT interrupt is requested
+
+mod common;
+use common::*;
+use dmg_2025::Emulator;
+
+const LCD_INTERRUPT: u8 = 0b0000_0010;
+
+/// Returns the PPU mode from STAT
+fn mode(emulator: &mut Emulator) -> u8 {
+    emulator.cpu.read(0xFF41) & 0b11
+}
+
+/// Runs the emulator until the PPU switches to given mode
+fn run_until_mode(emulator: &mut Emulator, mode_number: u8) {
+    while mode(emulator) != mode_number {
+        emulator.step_instruction();
+    }
+}
+
+fn lcd_interrupt_requested(emulator: &mut Emulator) -> bool {
+    emulator.cpu.read(0xFF0F) & LCD_INTERRUPT > 0
+}
+
+fn clear_interrupts(emulator: &mut Emulator) {
+    emulator.cpu.write(0xFF0F, 0);
+}
+
+#[test]
+fn lcd_off_resets_ly_and_mode() {
+    let mut emulator = scene_emulator();
+    run_until_line(&mut emulator, 50);
+    run_until_mode(&mut emulator, 3);
+
+    emulator.cpu.write(0xFF40, 0b0000_0000);
+    assert_eq!(emulator.cpu.read(0xFF44), 0);
+    assert_eq!(mode(&mut emulator), 0);
+    // PPU stays frozen while the LCD is off
+    emulator.run_frame();
+    emulator.run_frame();
+    assert_eq!(emulator.cpu.read(0xFF44), 0);
+    assert_eq!(mode(&mut emulator), 0);
+
+    // Turning the LCD back on starts from the first line
+    emulator.cpu.write(0xFF40, 0b1000_0000);
+    assert_eq!(emulator.cpu.read(0xFF44), 0);
+    run_until_mode(&mut emulator, 3);
+    assert_eq!(emulator.cpu.read(0xFF44), 0);
+    run_until_line(&mut emulator, 1);
+    assert_eq!(emulator.cpu.read(0xFF44), 1);
+}
+
+#[test]
+fn stat_source_doesnt_fire_while_line_is_high() {
+    let mut emulator = scene_emulator();
+    // Enable both the LY=LYC and the HBlank sources
+    emulator.cpu.write(0xFF45, 10);
+    emulator.cpu.write(0xFF41, 0b0100_1000);
+
+    // LY=LYC keeps the line high for the whole of line 10, so its HBlank doesn't fire
+    run_until_line(&mut emulator, 10);
+    run_until_mode(&mut emulator, 3);
+    clear_interrupts(&mut emulator);
+    run_until_mode(&mut emulator, 0);
+    assert!(!lcd_interrupt_requested(&mut emulator));
+
+    // Line goes low during OAM scan and drawing on the next line, so HBlank fires again
+    run_until_line(&mut emulator, 11);
+    run_until_mode(&mut emulator, 3);
+    clear_interrupts(&mut emulator);
+    run_until_mode(&mut emulator, 0);
+    assert!(lcd_interrupt_requested(&mut emulator));
+}
+
+#[test]
+fn stat_fires_on_rising_edge() {
+    let mut emulator = scene_emulator();
+    emulator.cpu.write(0xFF45, 20);
+    emulator.cpu.write(0xFF41, 0b0100_0000);
+    run_until_line(&mut emulator, 19);
+    clear_interrupts(&mut emulator);
+    run_until_line(&mut emulator, 20);
+    assert!(lcd_interrupt_requested(&mut emulator));
+
+    // Line stays high until LY changes, so the interrupt isn't requested again
+    clear_interrupts(&mut emulator);
+    run_until_mode(&mut emulator, 0);
+    assert!(!lcd_interrupt_requested(&mut emulator));
+}
@@ -0,0 +1,73 @@
+//! Two emulators exchanging bytes over a link cable on localhost
+
+use dmg_2025::link::{LinkCable, LinkHost};
+use dmg_2025::{Emulator, EmulatorOptions};
+use std::thread;
+
+/// Amount of bytes each side sends
+const TRANSFERS: u8 = 8;
+
+/// Returns a ROM that sends bytes starting from given one and stores the received bytes at $C000.
+/// Each byte is transferred with given value in SC, which picks the internal or external clock
+fn transfer_rom(first_byte: u8, control: u8) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    // NOP, JP $0150
+    rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    #[rustfmt::skip]
+    let program = [
+        0x21, 0x00, 0xC0,   // $0150: ld hl, $C000
+        0x06, first_byte,   // $0153: ld b, first_byte
+        0x78,               // $0155: loop: ld a, b
+        0xE0, 0x01,         // $0156: ldh [SB], a
+        0x3E, control,      // $0158: ld a, control
+        0xE0, 0x02,         // $015A: ldh [SC], a
+        0xF0, 0x02,         // $015C: wait: ldh a, [SC]
+        0xCB, 0x7F,         // $015E: bit 7, a
+        0x20, 0xFA,         // $0160: jr nz, wait
+        0xF0, 0x01,         // $0162: ldh a, [SB]
+        0x22,               // $0164: ld [hl+], a
+        0x04,               // $0165: inc b
+        0x7D,               // $0166: ld a, l
+        0xFE, TRANSFERS,    // $0167: cp TRANSFERS
+        0x20, 0xEA,         // $0169: jr nz, loop
+        0x18, 0xFE,         // $016B: jr @
+    ];
+    rom[0x0150..0x0150 + program.len()].copy_from_slice(&program);
+    rom
+}
+
+/// Runs the ROM for one frame on a separate thread and returns the bytes it received.
+/// Emulator is created on that thread, since two of them don't fit on the stack of a test
+fn run_frame(rom: Vec<u8>, cable: LinkCable) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut emulator = Emulator::new(rom, EmulatorOptions::default()).unwrap();
+        emulator.cpu.mem.boot_rom_mapped = false;
+        emulator.cpu.reg.pc = 0x0100;
+        emulator.connect_serial(Box::new(cable));
+        emulator.run_frame();
+        (0..TRANSFERS as u16)
+            .map(|i| emulator.cpu.read(0xC000 + i))
+            .collect()
+    })
+}
+
+#[test]
+fn bytes_are_exchanged_within_a_frame() {
+    // Bind to any free port, so tests running in parallel don't collide
+    let host = LinkHost::bind(0).unwrap();
+    let guest_cable = LinkCable::join(host.port().unwrap()).unwrap();
+    let host_cable = loop {
+        if let Some(cable) = host.try_accept().unwrap() {
+            break cable;
+        }
+    };
+
+    // Host clocks the transfers, and the guest waits for them
+    let host_thread = run_frame(transfer_rom(0x01, 0x81), host_cable);
+    let guest_thread = run_frame(transfer_rom(0xA0, 0x80), guest_cable);
+    let host_received = host_thread.join().unwrap();
+    let guest_received = guest_thread.join().unwrap();
+
+    assert_eq!(host_received, (0xA0..0xA0 + TRANSFERS).collect::<Vec<u8>>());
+    assert_eq!(guest_received, (0x01..=TRANSFERS).collect::<Vec<u8>>());
+}
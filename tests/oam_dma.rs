@@ -0,0 +1,75 @@
+//! Timing of OAM DMA transfers, which copy one byte per M-cycle
+
+mod common;
+use common::*;
+use dmg_2025::Emulator;
+
+/// Fills WRAM page $C000 and $D000 with different patterns and starts a transfer from given page
+fn start_transfer(source: u8) -> Emulator {
+    let mut emulator = scene_emulator();
+    for i in 0..0xA0 {
+        emulator.cpu.write(0xC000 + i, i as u8);
+        emulator.cpu.write(0xD000 + i, 0xFF - i as u8);
+    }
+    emulator.cpu.write(0xFF46, source);
+    // Write itself takes an M-cycle
+    emulator.cpu.cycle(1);
+    emulator
+}
+
+/// Cycles the system one M-cycle at a time, since `CPU::cycle` counts T-cycles in a byte
+fn run_m_cycles(emulator: &mut Emulator, cycles: u16) {
+    for _ in 0..cycles {
+        emulator.cpu.cycle(1);
+    }
+}
+
+#[test]
+fn dma_copies_a_byte_per_m_cycle() {
+    let mut emulator = start_transfer(0xC0);
+    // Transfer starts after a delay, until which memory can still be accessed
+    assert!(!emulator.cpu.ppu.oam_dma.active);
+    assert_eq!(emulator.cpu.read(0xC000), 0x00);
+    emulator.cpu.cycle(1);
+    assert!(emulator.cpu.ppu.oam_dma.active);
+
+    run_m_cycles(&mut emulator, 10);
+    assert_eq!(emulator.cpu.ppu.oam.read(9), 9);
+    assert_eq!(emulator.cpu.ppu.oam.read(10), 0);
+    // Only HRAM and I/O registers can be accessed during the transfer
+    assert_eq!(emulator.cpu.read(0xC000), 0xFF);
+    emulator.cpu.write(0xFF80, 0x12);
+    assert_eq!(emulator.cpu.read(0xFF80), 0x12);
+
+    run_m_cycles(&mut emulator, 150);
+    assert!(!emulator.cpu.ppu.oam_dma.active);
+    assert_eq!(emulator.cpu.ppu.oam.read(0x9F), 0x9F);
+    assert_eq!(emulator.cpu.read(0xC000), 0x00);
+}
+
+#[test]
+fn dma_restarts_when_rewritten() {
+    let mut emulator = start_transfer(0xC0);
+    run_m_cycles(&mut emulator, 21);
+    emulator.cpu.write(0xFF46, 0xD0);
+    emulator.cpu.cycle(1);
+    // Old transfer keeps going until the new one starts
+    assert_eq!(emulator.cpu.ppu.oam_dma.source, 0xC0);
+    emulator.cpu.cycle(1);
+    assert_eq!(emulator.cpu.ppu.oam_dma.source, 0xD0);
+    assert_eq!(emulator.cpu.ppu.oam.read(21), 21);
+    assert_eq!(emulator.cpu.read(0xFF46), 0xD0);
+
+    run_m_cycles(&mut emulator, 160);
+    assert!(!emulator.cpu.ppu.oam_dma.active);
+    assert_eq!(emulator.cpu.ppu.oam.read(0), 0xFF);
+    assert_eq!(emulator.cpu.ppu.oam.read(21), 0xEA);
+}
+
+#[test]
+fn dma_from_echo_ram_reads_wram() {
+    let mut emulator = start_transfer(0xF0);
+    run_m_cycles(&mut emulator, 161);
+    assert_eq!(emulator.cpu.ppu.oam.read(0), 0xFF);
+    assert_eq!(emulator.cpu.ppu.oam.read(0x9F), 0x60);
+}
@@ -0,0 +1,235 @@
+//! Object priority and 8x16 objects
+
+mod common;
+use common::*;
+use dmg_2025::Emulator;
+use std::ops::Range;
+
+/// LCD, objects and background on, tile data at $8000
+const LCDC: u8 = 0b1001_0011;
+
+/// Tile with a frame of color 3 and a transparent middle
+const RING: [u8; 16] = [
+    0xFF, 0xFF, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0xFF, 0xFF,
+];
+/// Tile with a diagonal line of color 2, which shows which way the tile is flipped
+const DIAGONAL: [u8; 16] = [
+    0x00, 0xC0, 0x00, 0x60, 0x00, 0x30, 0x00, 0x18, 0x00, 0x0C, 0x00, 0x06, 0x00, 0x03, 0x00, 0x01,
+];
+/// Tile with a horizontal bar of color 1 on the top rows
+const TOP_BAR: [u8; 16] = [
+    0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Sets up a background with a stripe of color 2 in the middle, and the object tiles:
+/// 1 is solid color 1, 2 is solid color 3, 3 is a ring, 4 is a diagonal and 5 has a bar on top
+fn sprite_scene() -> Emulator {
+    let mut emulator = scene_emulator();
+    write_tile(&mut emulator, 0, solid_tile(0));
+    write_tile(&mut emulator, 1, solid_tile(1));
+    write_tile(&mut emulator, 2, solid_tile(3));
+    write_tile(&mut emulator, 3, RING);
+    write_tile(&mut emulator, 4, DIAGONAL);
+    write_tile(&mut emulator, 5, TOP_BAR);
+    write_tile(&mut emulator, 6, solid_tile(2));
+    fill_tile_map(
+        &mut emulator,
+        0x9800,
+        |x, _| if x == 9 || x == 10 { 6 } else { 0 },
+    );
+    // Hide all objects
+    for address in 0xFE00..=0xFE9F {
+        emulator.cpu.write(address, 0);
+    }
+    emulator
+}
+
+/// Writes an object to OAM, with the position in object space
+fn write_sprite(emulator: &mut Emulator, index: u16, y: u8, x: u8, tile: u8, flags: u8) {
+    let address = 0xFE00 + index * 4;
+    for (i, value) in [y, x, tile, flags].into_iter().enumerate() {
+        emulator.cpu.write(address + i as u16, value);
+    }
+}
+
+/// Checks that every pixel in given area of the screen has given shade
+fn assert_area(emulator: &Emulator, xs: Range<u8>, ys: Range<u8>, shade: u16) {
+    for y in ys {
+        for x in xs.clone() {
+            assert_eq!(pixel(emulator, x, y), shade, "at ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn objects_further_left_are_drawn_on_top() {
+    let mut emulator = sprite_scene();
+    // Object later in OAM is drawn on top, since it's further left
+    write_sprite(&mut emulator, 0, 24, 44, 1, 0);
+    write_sprite(&mut emulator, 1, 28, 40, 2, 0);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator.run_frame();
+
+    assert_area(&emulator, 36..44, 8..12, 1);
+    assert_area(&emulator, 32..36, 12..20, 3);
+    assert_area(&emulator, 36..40, 12..16, 3);
+    assert_area(&emulator, 40..44, 12..16, 1);
+    assert_area(&emulator, 36..40, 16..20, 3);
+}
+
+#[test]
+fn only_ten_objects_are_drawn_on_a_line() {
+    let mut emulator = sprite_scene();
+    // Objects are picked in OAM order, so the last one is left out even though it's
+    // the furthest left
+    for i in 0..11 {
+        write_sprite(&mut emulator, i, 56, 136 - i as u8 * 12, 2, 0);
+    }
+    // Objects on other lines don't count towards the limit
+    write_sprite(&mut emulator, 11, 72, 16, 1, 0);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator.run_frame();
+
+    for i in 0..10 {
+        let x = 128 - i * 12;
+        assert_area(&emulator, x..x + 8, 40..48, 3);
+    }
+    assert_area(&emulator, 8..16, 40..48, 0);
+    assert_area(&emulator, 8..16, 56..64, 1);
+}
+
+#[test]
+fn background_priority_hides_object_under_it() {
+    let mut emulator = sprite_scene();
+    // Object with priority over the background is still drawn over color 0 of the background,
+    // and its pixels hide the object under it even where the background covers them
+    write_sprite(&mut emulator, 0, 80, 76, 3, 0b1000_0000);
+    write_sprite(&mut emulator, 1, 82, 78, 1, 0);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator.run_frame();
+
+    // Left edge of the ring is over the blank background
+    assert_area(&emulator, 68..69, 64..72, 3);
+    assert_area(&emulator, 68..72, 64..65, 3);
+    // Rest of the ring is over the stripe, which covers both objects
+    assert_area(&emulator, 72..76, 64..65, 2);
+    assert_area(&emulator, 75..76, 66..72, 2);
+    // Object under the ring shows through its transparent middle, and under the stripe
+    // where the ring doesn't cover it
+    assert_area(&emulator, 70..75, 66..71, 1);
+    assert_area(&emulator, 76..78, 66..74, 1);
+    assert_area(&emulator, 70..78, 72..74, 1);
+}
+
+/// Returns the shades of a row of the diagonal tile, with the object palette 0
+fn diagonal_row(row: usize, x_flip: bool) -> [u16; 8] {
+    let mut shades = std::array::from_fn(|x| if x == row || x == row + 1 { 2 } else { 0 });
+    if x_flip {
+        shades.reverse();
+    }
+    shades
+}
+
+/// Checks the shades of 8 pixels starting from given position
+fn assert_row(emulator: &Emulator, x: u8, y: u8, shades: [u16; 8]) {
+    let row: Vec<u16> = (x..x + 8).map(|x| pixel(emulator, x, y)).collect();
+    assert_eq!(row, shades, "at ({x}, {y})");
+}
+
+#[test]
+fn same_x_is_drawn_in_oam_order() {
+    let mut emulator = sprite_scene();
+    write_sprite(&mut emulator, 0, 24, 20, 1, 0);
+    write_sprite(&mut emulator, 1, 28, 20, 2, 0);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator.run_frame();
+
+    assert_area(&emulator, 12..20, 8..16, 1);
+    assert_area(&emulator, 12..20, 16..20, 3);
+}
+
+#[test]
+fn transparent_pixels_show_object_under_it() {
+    let mut emulator = sprite_scene();
+    // Ring is drawn on top, since it's further left and earlier in OAM
+    write_sprite(&mut emulator, 0, 24, 68, 3, 0);
+    write_sprite(&mut emulator, 1, 26, 70, 1, 0);
+    // Object later in OAM shows through even with the same X
+    write_sprite(&mut emulator, 2, 48, 100, 3, 0);
+    write_sprite(&mut emulator, 3, 48, 100, 1, 0);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator.run_frame();
+
+    assert_area(&emulator, 60..68, 8..9, 3);
+    assert_area(&emulator, 60..61, 8..16, 3);
+    assert_area(&emulator, 67..68, 8..16, 3);
+    assert_area(&emulator, 60..68, 15..16, 3);
+    assert_area(&emulator, 61..62, 9..15, 0);
+    assert_area(&emulator, 62..67, 9..10, 0);
+    assert_area(&emulator, 62..67, 10..15, 1);
+    assert_area(&emulator, 68..70, 10..18, 1);
+    assert_area(&emulator, 62..70, 16..18, 1);
+
+    assert_area(&emulator, 92..100, 32..33, 3);
+    assert_area(&emulator, 93..99, 33..39, 1);
+    assert_area(&emulator, 92..100, 39..40, 3);
+}
+
+#[test]
+fn tall_objects_use_tile_pairs() {
+    let mut emulator = sprite_scene();
+    // Odd tile index uses the even tile on top and the odd one at the bottom
+    write_sprite(&mut emulator, 0, 24, 20, 5, 0);
+    write_sprite(&mut emulator, 1, 24, 36, 4, 0);
+    // Object partly above the screen only shows its bottom tile
+    write_sprite(&mut emulator, 2, 8, 100, 4, 0);
+    emulator.cpu.write(0xFF40, LCDC | 0b0000_0100);
+    emulator.run_frame();
+
+    for x in [12, 28] {
+        for row in 0..8 {
+            assert_row(&emulator, x, 8 + row as u8, diagonal_row(row, false));
+        }
+        assert_area(&emulator, x..x + 8, 16..18, 1);
+        assert_area(&emulator, x..x + 8, 18..24, 0);
+    }
+    assert_area(&emulator, 92..100, 0..2, 1);
+    assert_area(&emulator, 92..100, 2..8, 0);
+}
+
+#[test]
+fn y_flip_swaps_tall_object_tiles() {
+    let mut emulator = sprite_scene();
+    write_sprite(&mut emulator, 0, 24, 52, 5, 0b0100_0000);
+    write_sprite(&mut emulator, 1, 24, 68, 4, 0b0110_0000);
+    emulator.cpu.write(0xFF40, LCDC | 0b0000_0100);
+    emulator.run_frame();
+
+    for (x, x_flip) in [(44, false), (60, true)] {
+        // Flipped bottom tile is on top, with its bar at the bottom
+        assert_area(&emulator, x..x + 8, 8..14, 0);
+        assert_area(&emulator, x..x + 8, 14..16, 1);
+        for row in 0..8 {
+            assert_row(&emulator, x, 23 - row as u8, diagonal_row(row, x_flip));
+        }
+    }
+}
+
+#[test]
+fn overlapping_tall_objects() {
+    let mut emulator = sprite_scene();
+    // Solid color 3 on top and a ring at the bottom
+    write_sprite(&mut emulator, 0, 60, 124, 3, 0);
+    // Transparent on top and solid color 1 at the bottom, drawn with the inverted palette
+    write_sprite(&mut emulator, 1, 64, 124, 1, 0b0001_0000);
+    emulator.cpu.write(0xFF40, LCDC | 0b0000_0100);
+    emulator.run_frame();
+
+    assert_area(&emulator, 116..124, 44..53, 3);
+    assert_area(&emulator, 116..117, 52..60, 3);
+    assert_area(&emulator, 123..124, 52..60, 3);
+    assert_area(&emulator, 116..124, 59..60, 3);
+    assert_area(&emulator, 117..123, 53..56, 0);
+    assert_area(&emulator, 117..123, 56..59, 2);
+    assert_area(&emulator, 116..124, 60..64, 2);
+}
@@ -0,0 +1,291 @@
+//! Conformance harness for test ROMs that are supplied locally.
+//!
+//! ROMs are searched recursively from the directory set in the `DMG_TEST_ROMS` environment variable,
+//! or from `test_roms/` in the repository root. The way a ROM reports its result is picked like this:
+//! - If a PNG with the same name is next to the ROM (e.g. `dmg-acid2.gb` and `dmg-acid2.png`),
+//!   the screen is compared against it after the ROM executes `LD B,B`
+//! - Mooneye tests (anything with `mooneye` in its path) execute `LD B,B` when done,
+//!   and leave the Fibonacci sequence in registers B-L on success
+//! - Everything else is expected to print "Passed" or "Failed" over the serial port like blargg's tests
+//!
+//! ROMs listed in `tests/rom_pass_list.txt` must be found with the same SHA-1 hash and pass,
+//! so regressions get caught.
+//! The harness is ignored by default since the ROMs aren't part of the repository.
+//! Emulation is slow in debug builds, so run it with
+//! `cargo test --release --test test_roms -- --ignored --nocapture` to see the full results.
+
+use dmg_2025::{cpu::serial::SerialDevice, Emulator, EmulatorOptions, DMG_SHADES};
+use sha1::{Digest, Sha1};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Maximum amount of emulated frames before a test is timed out (about two minutes)
+const MAX_FRAMES: u32 = 60 * 120;
+/// Opcode of `LD B,B`, which test ROMs use as a software breakpoint
+const LD_B_B: u8 = 0x40;
+
+enum Outcome {
+    Pass,
+    Fail(String),
+    Timeout,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Pass => write!(f, "PASS"),
+            Outcome::Fail(reason) => write!(f, "FAIL ({reason})"),
+            Outcome::Timeout => write!(f, "FAIL (timed out)"),
+        }
+    }
+}
+
+/// Executes instructions until given check returns an outcome, or the frame limit is hit
+fn run_until(
+    emulator: &mut Emulator,
+    mut check: impl FnMut(&mut Emulator) -> Option<Outcome>,
+) -> Outcome {
+    let mut frames = 0;
+    while frames < MAX_FRAMES {
+        if let Some(outcome) = check(emulator) {
+            return outcome;
+        }
+        if emulator.step_instruction() {
+            frames += 1;
+        }
+    }
+    Outcome::Timeout
+}
+
+fn at_breakpoint(emulator: &Emulator) -> bool {
+    emulator.cpu.read_unrestricted(emulator.cpu.reg.pc) == LD_B_B
+}
+
+/// Serial device that collects everything sent by the Game Boy
+struct SerialCapture(Arc<Mutex<String>>);
+
+impl SerialDevice for SerialCapture {
+    fn transfer(&mut self, byte: u8) -> u8 {
+        self.0.lock().unwrap().push(byte as char);
+        0xFF
+    }
+}
+
+/// Blargg's tests print their results as text over the serial port
+fn run_serial_test(emulator: &mut Emulator) -> Outcome {
+    let output = Arc::new(Mutex::new(String::new()));
+    emulator.connect_serial(Box::new(SerialCapture(output.clone())));
+    run_until(emulator, |_| {
+        let output = output.lock().unwrap();
+        if output.contains("Passed") {
+            Some(Outcome::Pass)
+        } else if output.contains("Failed") {
+            Some(Outcome::Fail(output.trim().replace('\n', " ")))
+        } else {
+            None
+        }
+    })
+}
+
+/// Mooneye tests load the Fibonacci sequence into registers on success
+fn run_mooneye_test(emulator: &mut Emulator) -> Outcome {
+    run_until(emulator, |emulator| {
+        if !at_breakpoint(emulator) {
+            return None;
+        }
+        let reg = &emulator.cpu.reg;
+        let registers = [reg.b, reg.c, reg.d, reg.e, reg.h, reg.l];
+        if registers == [3, 5, 8, 13, 21, 34] {
+            Some(Outcome::Pass)
+        } else {
+            Some(Outcome::Fail(format!("registers {registers:?}")))
+        }
+    })
+}
+
+/// Compares the screen to a reference image after the ROM hits a breakpoint
+fn run_screenshot_test(emulator: &mut Emulator, rom_path: &Path, reference_path: &Path) -> Outcome {
+    let reference = match image::open(reference_path) {
+        Ok(image) => image.to_luma8(),
+        Err(e) => return Outcome::Fail(format!("couldn't open reference image: {e}")),
+    };
+    if reference.dimensions() != (160, 144) {
+        return Outcome::Fail("reference image isn't 160x144".into());
+    }
+
+    let outcome = run_until(emulator, |emulator| {
+        at_breakpoint(emulator).then_some(Outcome::Pass)
+    });
+    if !matches!(outcome, Outcome::Pass) {
+        return outcome;
+    }
+    // Let the PPU finish drawing the current frame
+    emulator.run_frame();
+
+    let display = emulator.framebuffer();
+    let mut mismatches = 0;
+    for (x, y, pixel) in reference.enumerate_pixels() {
+        // Map reference shade to the closest color ID
+        let expected = (0..4)
+            .min_by_key(|&i| DMG_SHADES[i].abs_diff(pixel.0[0]))
+            .unwrap() as u16;
+        if display[x as usize][y as usize] != expected {
+            mismatches += 1;
+        }
+    }
+    if mismatches == 0 {
+        return Outcome::Pass;
+    }
+
+    // Save the actual screen for comparison
+    let output_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join(rom_path.file_stem().unwrap())
+        .with_extension("png");
+    let _ = emulator.screenshot().save(&output_path);
+    Outcome::Fail(format!(
+        "{mismatches} pixels differ, screen saved to {}",
+        output_path.display()
+    ))
+}
+
+fn run_test_rom(rom_path: &Path) -> Outcome {
+    let rom_file = match std::fs::read(rom_path) {
+        Ok(rom_file) => rom_file,
+        Err(e) => return Outcome::Fail(format!("couldn't read ROM: {e}")),
+    };
+    let mut emulator = match Emulator::new(rom_file, EmulatorOptions::default()) {
+        Ok(emulator) => emulator,
+        Err(e) => return Outcome::Fail(e.to_string()),
+    };
+
+    let reference_path = rom_path.with_extension("png");
+    if reference_path.exists() {
+        run_screenshot_test(&mut emulator, rom_path, &reference_path)
+    } else if rom_path.to_string_lossy().contains("mooneye") {
+        run_mooneye_test(&mut emulator)
+    } else {
+        run_serial_test(&mut emulator)
+    }
+}
+
+/// Returns paths of all ROM files under given directory
+fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = vec![];
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return roms;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            roms.extend(find_roms(&path));
+        } else if path.extension().is_some_and(|ext| ext == "gb") {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    roms
+}
+
+/// ROM that is expected to pass
+struct PassListEntry {
+    /// SHA-1 hash of the ROM file as a hex string
+    hash: String,
+    /// Path relative to the ROM directory
+    name: String,
+}
+
+/// Reads the ROMs that are expected to pass. Each line is in the format `sha1sum` prints
+fn read_pass_list() -> Vec<PassListEntry> {
+    include_str!("rom_pass_list.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (hash, name) = line
+                .split_once(char::is_whitespace)
+                .unwrap_or_else(|| panic!("Invalid line in pass list: {line}"));
+            PassListEntry {
+                hash: hash.to_lowercase(),
+                // Binary mode marks the file name with an asterisk
+                name: name.trim_start().trim_start_matches('*').to_string(),
+            }
+        })
+        .collect()
+}
+
+fn sha1_hex(path: &Path) -> String {
+    let file = std::fs::read(path).unwrap();
+    format!("{:x}", Sha1::digest(file))
+}
+
+#[test]
+#[ignore = "needs test ROMs in test_roms/ or DMG_TEST_ROMS"]
+fn test_roms() {
+    let rom_dir = std::env::var_os("DMG_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_roms"));
+    assert!(
+        rom_dir.is_dir(),
+        "Test ROM directory {} doesn't exist. Put the ROMs there or set DMG_TEST_ROMS",
+        rom_dir.display()
+    );
+    let roms = find_roms(&rom_dir);
+    let names: Vec<String> = roms
+        .iter()
+        .map(|rom_path| {
+            rom_path
+                .strip_prefix(&rom_dir)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+
+    // Every ROM in the pass list has to be there as the same version,
+    // otherwise a regression could go unnoticed
+    let pass_list = read_pass_list();
+    let mut problems = vec![];
+    for entry in &pass_list {
+        let rom_path = rom_dir.join(&entry.name);
+        if !names.contains(&entry.name) {
+            problems.push(format!("{} is missing", rom_path.display()));
+        } else if sha1_hex(&rom_path) != entry.hash {
+            problems.push(format!(
+                "{} has a different SHA-1 hash than {}",
+                rom_path.display(),
+                entry.hash
+            ));
+        }
+    }
+    assert!(
+        problems.is_empty(),
+        "Test ROMs in pass list can't be checked:\n{}",
+        problems.join("\n")
+    );
+
+    let mut regressions = vec![];
+    let mut passed = 0;
+    for (rom_path, name) in roms.iter().zip(names) {
+        let outcome = run_test_rom(rom_path);
+        let expected = pass_list.iter().any(|entry| entry.name == name);
+        match outcome {
+            Outcome::Pass => {
+                passed += 1;
+                if !expected {
+                    println!("{outcome}  {name}  (not in pass list yet)");
+                    continue;
+                }
+            }
+            _ if expected => regressions.push(name.clone()),
+            _ => {}
+        }
+        println!("{outcome}  {name}");
+    }
+    println!("{passed}/{} test ROMs passed", roms.len());
+
+    assert!(
+        regressions.is_empty(),
+        "Test ROMs in pass list failed: {regressions:?}"
+    );
+}
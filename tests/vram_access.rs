@@ -0,0 +1,70 @@
+//! CPU access to VRAM and OAM while the PPU is using them
+
+mod common;
+use common::*;
+use dmg_2025::Emulator;
+
+/// Returns the PPU mode from STAT
+fn mode(emulator: &mut Emulator) -> u8 {
+    emulator.cpu.read(0xFF41) & 0b11
+}
+
+/// Runs the emulator until the PPU switches to given mode
+fn run_until_mode(emulator: &mut Emulator, mode_number: u8) {
+    while mode(emulator) != mode_number {
+        emulator.step_instruction();
+    }
+}
+
+/// Returns an emulator in VBlank with a byte written to the start of VRAM and OAM
+fn emulator_with_data() -> Emulator {
+    let mut emulator = scene_emulator();
+    emulator.cpu.write(0x8000, 0x12);
+    emulator.cpu.write(0xFE00, 0x34);
+    emulator
+}
+
+#[test]
+fn oam_is_blocked_during_oam_scan() {
+    let mut emulator = emulator_with_data();
+    run_until_line(&mut emulator, 5);
+    run_until_mode(&mut emulator, 2);
+    assert_eq!(emulator.cpu.read(0xFE00), 0xFF);
+    assert_eq!(emulator.cpu.read(0x8000), 0x12);
+}
+
+#[test]
+fn vram_and_oam_are_blocked_while_drawing() {
+    let mut emulator = emulator_with_data();
+    run_until_line(&mut emulator, 5);
+    run_until_mode(&mut emulator, 3);
+    assert_eq!(emulator.cpu.read(0x8000), 0xFF);
+    assert_eq!(emulator.cpu.read(0xFE00), 0xFF);
+    // Writes are ignored as well
+    emulator.cpu.write(0x8000, 0x56);
+    emulator.cpu.write(0xFE00, 0x78);
+
+    run_until_mode(&mut emulator, 0);
+    assert_eq!(emulator.cpu.read(0x8000), 0x12);
+    assert_eq!(emulator.cpu.read(0xFE00), 0x34);
+}
+
+#[test]
+fn everything_can_be_accessed_with_lcd_off() {
+    let mut emulator = emulator_with_data();
+    run_until_line(&mut emulator, 5);
+    run_until_mode(&mut emulator, 3);
+    emulator.cpu.write(0xFF40, 0b0000_0000);
+    assert_eq!(emulator.cpu.read(0x8000), 0x12);
+    assert_eq!(emulator.cpu.read(0xFE00), 0x34);
+}
+
+#[test]
+fn restrictions_can_be_turned_off() {
+    let mut emulator = emulator_with_data();
+    emulator.cpu.access_restrictions = false;
+    run_until_line(&mut emulator, 5);
+    run_until_mode(&mut emulator, 3);
+    assert_eq!(emulator.cpu.read(0x8000), 0x12);
+    assert_eq!(emulator.cpu.read(0xFE00), 0x34);
+}
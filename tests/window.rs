@@ -0,0 +1,141 @@
+//! The window, which has its own line counter and position quirks
+
+mod common;
+use common::*;
+use dmg_2025::Emulator;
+
+/// LCD and window on, window uses the tile map at $9C00 and tile data at $8000
+const LCDC: u8 = 0b1111_0001;
+
+/// Sets up a blank background and a window whose rows can be told apart.
+/// Every window row has its own tile and row number in the tile encoded into its pixels,
+/// and the left-most pixel of every window tile is color 1
+fn window_scene() -> Emulator {
+    let mut emulator = scene_emulator();
+    write_tile(&mut emulator, 0, solid_tile(0));
+    for tile in 1..=32u8 {
+        let data = std::array::from_fn(|i| {
+            let row = (i / 2) as u8;
+            if i % 2 == 0 {
+                0x80 | row
+            } else {
+                tile
+            }
+        });
+        write_tile(&mut emulator, tile, data);
+    }
+    fill_tile_map(&mut emulator, 0x9800, |_, _| 0);
+    fill_tile_map(&mut emulator, 0x9C00, |_, y| y as u8 + 1);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator
+}
+
+/// Returns the window row drawn on a line, read from the window tile starting at given X.
+/// Returns None if the background is drawn there instead
+fn window_row(emulator: &Emulator, x: u8, y: u8) -> Option<u8> {
+    let (low, high) = (0..8).fold((0u8, 0u8), |(low, high), i| {
+        let color = pixel(emulator, x + i, y) as u8;
+        ((low << 1) | (color & 1), (high << 1) | (color >> 1))
+    });
+    (low & 0x80 > 0).then(|| (high - 1) * 8 + (low & 0b111))
+}
+
+/// Checks that given lines only show the background left of given X,
+/// and the window rows starting from `first_row` from there on
+fn assert_window_rows(emulator: &Emulator, lines: std::ops::Range<u8>, x: u8, first_row: u8) {
+    for (row, y) in (first_row..).zip(lines) {
+        for background_x in 0..x {
+            assert_eq!(
+                pixel(emulator, background_x, y),
+                0,
+                "at ({background_x}, {y})"
+            );
+        }
+        assert_eq!(window_row(emulator, x, y), Some(row), "on line {y}");
+    }
+}
+
+/// Checks that given lines only show the background
+fn assert_background(emulator: &Emulator, lines: std::ops::Range<u8>) {
+    for y in lines {
+        for x in 0..160 {
+            assert_eq!(pixel(emulator, x, y), 0, "at ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn window_line_counter_pauses_while_hidden() {
+    let mut emulator = window_scene();
+    emulator.cpu.write(0xFF4A, 16);
+    emulator.cpu.write(0xFF4B, 15);
+    run_until_line(&mut emulator, 48);
+    emulator.cpu.write(0xFF40, LCDC & !0b0010_0000);
+    run_until_line(&mut emulator, 72);
+    emulator.cpu.write(0xFF40, LCDC);
+    emulator.run_frame();
+
+    assert_background(&emulator, 0..16);
+    assert_window_rows(&emulator, 16..48, 8, 0);
+    assert_background(&emulator, 48..72);
+    // Window continues from row 32 instead of 56 when it's shown again
+    assert_window_rows(&emulator, 72..144, 8, 32);
+}
+
+#[test]
+fn window_needs_wy_to_match_ly() {
+    let mut emulator = window_scene();
+    emulator.cpu.write(0xFF4A, 200);
+    emulator.cpu.write(0xFF4B, 7);
+    // WY is set to a line that has already been drawn, so the window doesn't show up
+    run_until_line(&mut emulator, 20);
+    emulator.cpu.write(0xFF4A, 10);
+    // Window starts once LY matches WY
+    run_until_line(&mut emulator, 40);
+    emulator.cpu.write(0xFF4A, 40);
+    // Moving WY after that doesn't hide the window for the rest of the frame
+    run_until_line(&mut emulator, 60);
+    emulator.cpu.write(0xFF4A, 100);
+    // Moving WX keeps drawing the next window row at the new position
+    run_until_line(&mut emulator, 80);
+    emulator.cpu.write(0xFF4B, 87);
+    emulator.run_frame();
+
+    assert_background(&emulator, 0..40);
+    assert_window_rows(&emulator, 40..80, 0, 0);
+    assert_window_rows(&emulator, 80..144, 80, 40);
+}
+
+#[test]
+fn window_at_screen_edges() {
+    let mut emulator = window_scene();
+    emulator.cpu.write(0xFF4A, 0);
+    emulator.cpu.write(0xFF4B, 0);
+    run_until_line(&mut emulator, 48);
+    emulator.cpu.write(0xFF4B, 3);
+    run_until_line(&mut emulator, 96);
+    emulator.cpu.write(0xFF4B, 166);
+    emulator.run_frame();
+
+    // WX under 7 moves the window past the left edge, so the first pixels of the window are hidden
+    for y in 0..48 {
+        assert_eq!(window_row(&emulator, 1, y), Some(y), "on line {y}");
+        assert_eq!(window_row(&emulator, 145, y), Some(y), "on line {y}");
+    }
+    for y in 48..96 {
+        assert_eq!(window_row(&emulator, 4, y), Some(y), "on line {y}");
+        assert_eq!(window_row(&emulator, 148, y), Some(y), "on line {y}");
+    }
+
+    // WX=166 only shows the first pixel of the window on the last pixel of the line
+    for x in 0..159 {
+        assert_eq!(pixel(&emulator, x, 96), 0, "at ({x}, 96)");
+    }
+    assert_eq!(pixel(&emulator, 159, 96), 1);
+    // Every match makes the window cover the whole next line,
+    // and the line counter advances on every line the window was drawn on
+    assert_window_rows(&emulator, 97..144, 0, 97);
+    for y in 97..144 {
+        assert_eq!(window_row(&emulator, 152, y), Some(y), "on line {y}");
+    }
+}